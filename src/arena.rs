@@ -0,0 +1,304 @@
+//! Match runner to compare players.
+//!
+//! Two players play games from the same random openings with colors
+//! swapped in turn.  Besides the results, thinking time and disc
+//! balance are collected for each game phase, so that strengths and
+//! weaknesses of different approaches show up.
+//!
+
+use crate::board::{Board, Turn};
+use crate::player::{Action, Player};
+use crate::position::Position;
+use crate::ui_board::UiBoard;
+use crate::utils;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::time::Duration;
+
+/// Names of game phases.
+pub const PHASES: [&str; 3] = ["opening", "midgame", "endgame"];
+
+/// Phase of `board` by the number of stones: up to 20 moves are the
+/// opening, and the last 20 holes are the endgame.
+fn phase(board: &Board) -> usize {
+    match board.count_stone() {
+        0..=23 => 0,
+        24..=43 => 1,
+        _ => 2,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PhaseStats {
+    /// Number of moves played in the phase.
+    pub moves: u32,
+    /// Thinking time for the moves.
+    pub time: Duration,
+    /// Number of games which reached the end of the phase.
+    pub games: u32,
+    /// Sum of the differences of disc counts at the end of the phase.
+    pub discs: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlayerStats {
+    pub name: String,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Sum of the final disc differentials.  A forfeited game counts
+    /// as lost by 64 discs, whatever remains on the board.
+    pub discs: i32,
+    pub phases: [PhaseStats; 3],
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchConfig {
+    /// Number of games; colors are swapped every game.
+    pub games: u32,
+    /// Number of random moves at the beginning of each pair of games.
+    pub random_plies: u32,
+    pub seed: u64,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            games: 10,
+            random_plies: 4,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MatchResult {
+    pub players: [PlayerStats; 2],
+}
+
+/// Play `config.games` games between `players` named `names`, and
+/// call `progress` after each game.
+///
+/// A player giving up or making an illegal move loses the game by
+/// 64 discs.
+pub fn play_match(
+    mut players: [&mut dyn Player; 2],
+    names: [&str; 2],
+    config: &MatchConfig,
+    mut progress: impl FnMut(u32, &MatchResult),
+) -> MatchResult {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut result = MatchResult::default();
+    let mut opening = Vec::new();
+
+    for (stats, name) in result.players.iter_mut().zip(names.iter()) {
+        stats.name = name.to_string();
+    }
+
+    for game in 0..config.games {
+        if game % 2 == 0 {
+            opening = random_opening(config.random_plies, &mut rng);
+        }
+        // players[black] is black in this game.
+        let black = (game % 2) as usize;
+        play_game(&mut players, black, &opening, &mut result);
+        progress(game + 1, &result);
+    }
+    result
+}
+
+fn random_opening(plies: u32, rng: &mut StdRng) -> Vec<Position> {
+    let mut board = UiBoard::new();
+    let mut moves = Vec::new();
+
+    for _ in 0..plies {
+        let legal: Vec<Position> = board.legal_moves().collect();
+        if legal.is_empty() {
+            break;
+        }
+        let pos = legal[rng.gen_range(0, legal.len())];
+        board.put_stone(pos).unwrap();
+        moves.push(pos);
+    }
+    moves
+}
+
+/// Own discs minus opponent's discs of `turn`.
+fn disc_difference(board: &Board, turn: Turn) -> i32 {
+    let difference = board.count_black() as i32 - board.count_white() as i32;
+
+    if turn.is_black() {
+        difference
+    } else {
+        -difference
+    }
+}
+
+fn play_game(
+    players: &mut [&mut dyn Player; 2],
+    black: usize,
+    opening: &[Position],
+    result: &mut MatchResult,
+) {
+    let mut board = UiBoard::new();
+    let mut phase_ends: [Option<Board>; 3] = [None, None, None];
+    let mut forfeit = None;
+    let index_of = |turn: Turn| if turn.is_black() { black } else { 1 - black };
+
+    for &pos in opening {
+        board.put_stone(pos).unwrap();
+    }
+
+    while let Some(turn) = board.whatnow() {
+        let index = index_of(turn);
+        let phase = phase(board.raw_board());
+        let start = utils::now_ms();
+        let action = players[index].action(&board);
+        let elapsed = utils::elapsed_since(start);
+
+        let stats = &mut result.players[index].phases[phase];
+        stats.moves += 1;
+        stats.time += elapsed;
+
+        let ok = match action {
+            Action::Move(pos) => board.put_stone(pos).is_ok(),
+            Action::Pass => board.pass().is_ok(),
+            // taking back moves is not allowed in a match.
+            Action::GiveUp | Action::Undo | Action::Redo => false,
+        };
+        if !ok {
+            forfeit = Some(index);
+            break;
+        }
+        phase_ends[phase] = Some(board.raw_board().clone());
+    }
+
+    for (phase, end) in phase_ends.iter().enumerate() {
+        if let Some(end) = end {
+            for turn in [Turn::Black, Turn::White].iter() {
+                let stats = &mut result.players[index_of(*turn)].phases[phase];
+                stats.games += 1;
+                stats.discs += disc_difference(end, *turn);
+            }
+        }
+    }
+
+    for turn in [Turn::Black, Turn::White].iter() {
+        let index = index_of(*turn);
+        let score = match forfeit {
+            Some(loser) if loser == index => -64,
+            Some(_) => 64,
+            None => board.raw_board().final_score(*turn),
+        };
+        let stats = &mut result.players[index];
+
+        stats.discs += score;
+        match score {
+            s if s > 0 => stats.wins += 1,
+            s if s < 0 => stats.losses += 1,
+            _ => stats.draws += 1,
+        }
+    }
+}
+
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let average = |sum: i32, n: u32| if n == 0 { 0.0 } else { sum as f64 / n as f64 };
+
+        writeln!(f, "player       wins draws losses   discs")?;
+        for p in &self.players {
+            let games = p.wins + p.draws + p.losses;
+            writeln!(
+                f,
+                "{:10} {:6} {:5} {:6} {:+7.1}",
+                p.name,
+                p.wins,
+                p.draws,
+                p.losses,
+                average(p.discs, games)
+            )?;
+        }
+
+        writeln!(f, "\nphase    player      moves  time/move   discs")?;
+        for (phase, name) in PHASES.iter().enumerate() {
+            for p in &self.players {
+                let stats = &p.phases[phase];
+                let time = if stats.moves == 0 {
+                    0.0
+                } else {
+                    stats.time.as_secs_f64() / stats.moves as f64
+                };
+                writeln!(
+                    f,
+                    "{:8} {:10} {:6} {:9.3}s {:+7.1}",
+                    name,
+                    p.name,
+                    stats.moves,
+                    time,
+                    average(stats.discs, stats.games)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mcts::MctsPlayer;
+    use crate::player::CleverRobotPlayer;
+
+    #[test]
+    fn match_counts_every_game() {
+        let mut clever = CleverRobotPlayer::new().with_seed(0);
+        let mut mcts = MctsPlayer::new().with_iterations(50).with_seed(0);
+        let config = MatchConfig {
+            games: 2,
+            ..MatchConfig::default()
+        };
+        let mut calls = 0;
+        let result = play_match(
+            [&mut clever, &mut mcts],
+            ["clever", "mcts"],
+            &config,
+            |_, _| calls += 1,
+        );
+
+        assert_eq!(calls, 2);
+        for p in &result.players {
+            assert_eq!(p.wins + p.draws + p.losses, 2);
+            assert_eq!(p.phases[0].games, 2);
+        }
+        assert_eq!(result.players[0].discs, -result.players[1].discs);
+        assert_eq!(result.players[0].wins, result.players[1].losses);
+    }
+
+    struct Quitter;
+
+    impl Player for Quitter {
+        fn action(&mut self, _board: &UiBoard) -> Action {
+            Action::GiveUp
+        }
+    }
+
+    #[test]
+    fn forfeits_lose_by_all_discs() {
+        let mut clever = CleverRobotPlayer::new().with_seed(0);
+        let config = MatchConfig {
+            games: 2,
+            ..MatchConfig::default()
+        };
+        let result = play_match(
+            [&mut clever, &mut Quitter],
+            ["clever", "quitter"],
+            &config,
+            |_, _| (),
+        );
+
+        assert_eq!(result.players[0].wins, 2);
+        assert_eq!(result.players[0].discs, 128);
+        assert_eq!(result.players[1].discs, -128);
+    }
+}
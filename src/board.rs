@@ -8,13 +8,15 @@
 //! keep it compact.
 //!
 
-use crate::bitboard::{self, BitBoard, Move, Moves};
+use crate::bitboard::{self, BitBoard, Move, MoveIter, Moves, Symmetry};
+use crate::zobrist;
 use std::fmt;
+use std::str::FromStr;
 use wasm_bindgen::prelude::*;
 
 /// Used to express the current player.
 #[wasm_bindgen]
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Turn {
     Black,
     White,
@@ -47,13 +49,24 @@ impl fmt::Display for Turn {
     }
 }
 
+/// Record to take back a move made by `Board::make_move`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Undo {
+    /// The move made; 0 for pass.
+    pub mov: Move,
+    /// Stones flipped by the move.
+    pub flipped: BitBoard,
+    /// The player who made the move.
+    pub turn: Turn,
+}
+
 /// Board is used to store:
 /// * positions of black and white stones
 /// * the current player
 ///
 /// and is used as a node of the game search tree, it is necessary to
 /// keep it compact.
-#[derive(Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
     pub black: BitBoard,
     pub white: BitBoard,
@@ -100,6 +113,53 @@ impl Board {
         }
     }
 
+    /// Zobrist hash of the board; see `zobrist` for incremental update.
+    pub fn zobrist_hash(&self) -> u64 {
+        zobrist::hash(self)
+    }
+
+    /// Rotated or mirrored copy of the board.
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        Board {
+            black: symmetry.apply(self.black),
+            white: symmetry.apply(self.white),
+            turn: self.turn,
+        }
+    }
+
+    /// The smallest of the 8 symmetric copies ordered by (black,
+    /// white), and the symmetry which makes it from `self`.
+    ///
+    /// Symmetric positions share the same canonical form.
+    pub fn canonical(&self) -> (Self, Symmetry) {
+        Symmetry::ALL
+            .iter()
+            .map(|&symmetry| (self.transform(symmetry), symmetry))
+            .min_by_key(|(board, _)| (board.black, board.white))
+            .unwrap()
+    }
+
+    /// One-line notation of the board read by `from_str`: 64 squares
+    /// from a1 to h8 of "X" (black), "O" (white) or "-", a space and
+    /// the side to move.
+    pub fn to_notation(&self) -> String {
+        let mut notation: String = (0..64)
+            .map(|i| {
+                let bit = (1 << 63) >> i;
+                if self.black & bit != 0 {
+                    'X'
+                } else if self.white & bit != 0 {
+                    'O'
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+
+        notation.push_str(if self.is_black_turn() { " X" } else { " O" });
+        notation
+    }
+
     pub fn count_reversible_stones(&self, mov: Move) -> u32 {
         self.reversible_stones(mov).count_ones()
     }
@@ -125,10 +185,59 @@ impl Board {
         }
     }
 
+    /// Put a stone at `mov` in place, and return a record to take it back
+    /// by `unmake_move`.
+    ///
+    /// Unlike `put_stone`, it allows search to walk the tree without
+    /// cloning boards.
+    pub fn make_move(&mut self, mov: Move) -> Undo {
+        let turn = self.turn;
+        let flipped = self.reversible_stones(mov);
+
+        if turn.is_black() {
+            self.black ^= mov | flipped;
+            self.white ^= flipped;
+        } else {
+            self.white ^= mov | flipped;
+            self.black ^= flipped;
+        }
+        self.turn = turn.opposit();
+        Undo { mov, flipped, turn }
+    }
+
+    /// Pass in place, and return a record to take it back by `unmake_move`.
+    pub fn make_pass(&mut self) -> Undo {
+        let turn = self.turn;
+
+        self.turn = turn.opposit();
+        Undo {
+            mov: 0,
+            flipped: 0,
+            turn,
+        }
+    }
+
+    /// Take back the move (or pass) recorded in `undo`.
+    pub fn unmake_move(&mut self, undo: Undo) {
+        if undo.turn.is_black() {
+            self.black ^= undo.mov | undo.flipped;
+            self.white ^= undo.flipped;
+        } else {
+            self.white ^= undo.mov | undo.flipped;
+            self.black ^= undo.flipped;
+        }
+        self.turn = undo.turn;
+    }
+
     pub fn legal_moves(&self) -> Moves {
         self.legal_moves_for_player(self.turn)
     }
 
+    /// Iterate legal moves without allocation.
+    pub fn legal_move_iter(&self) -> MoveIter {
+        MoveIter::new(self.legal_moves())
+    }
+
     fn legal_moves_for_player(&self, turn: Turn) -> Moves {
         if turn.is_black() {
             bitboard::legal_moves(self.black, self.white)
@@ -179,6 +288,45 @@ impl Board {
         vec
     }
 
+    /// Count leaf nodes of the game tree `depth` plies deep (perft).
+    ///
+    /// The tree is the same as `children` builds: a pass is a ply,
+    /// and a finished game is a leaf even if it ends before `depth`.
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let children = self.children();
+
+        if children.is_empty() {
+            // game over
+            1
+        } else if depth == 1 {
+            children.len() as u64
+        } else {
+            children.iter().map(|child| child.perft(depth - 1)).sum()
+        }
+    }
+
+    /// Final disc differential seen from `turn`.
+    ///
+    /// Holes left at the end of the game are counted for the winner.
+    pub fn final_score(&self, turn: Turn) -> i32 {
+        let (mine, theirs) = if turn.is_black() {
+            (self.count_black() as i32, self.count_white() as i32)
+        } else {
+            (self.count_white() as i32, self.count_black() as i32)
+        };
+        let holes = self.count_hole() as i32;
+
+        match mine - theirs {
+            diff if diff > 0 => diff + holes,
+            diff if diff < 0 => diff - holes,
+            _ => 0,
+        }
+    }
+
     pub fn eval_score(&self, turn: Turn) -> i32 {
         self.eval_score_single(turn) - self.eval_score_single(turn.opposit())
     }
@@ -275,3 +423,165 @@ impl fmt::Display for Board {
         Ok(())
     }
 }
+
+/// Parse the notation of `Board::to_notation`.  Also accepted are
+/// "*" or "x" for black, "o" for white, "." for empty, and white
+/// space anywhere.
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(notation: &str) -> Result<Self, String> {
+        let chars: Vec<char> = notation.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut board = Board {
+            black: 0,
+            white: 0,
+            turn: Turn::Black,
+        };
+
+        if chars.len() != 65 {
+            return Err(format!(
+                "position needs 64 squares and a side to move, not {} letters",
+                chars.len()
+            ));
+        }
+        for (i, c) in chars[..64].iter().enumerate() {
+            let bit = (1 << 63) >> i;
+            match c {
+                'X' | 'x' | '*' => board.black |= bit,
+                'O' | 'o' => board.white |= bit,
+                '-' | '.' => (),
+                _ => return Err(format!("invalid square '{}'", c)),
+            }
+        }
+        board.turn = match chars[64] {
+            'X' | 'x' | '*' => Turn::Black,
+            'O' | 'o' => Turn::White,
+            c => return Err(format!("invalid side to move '{}'", c)),
+        };
+        Ok(board)
+    }
+}
+
+/// Play a deterministic pseudo-random game until `holes` remain, for
+/// tests of searches.
+#[cfg(test)]
+pub(crate) fn midgame_board(seed: u64, holes: u32) -> Board {
+    let mut board = Board::new();
+    let mut state = seed;
+
+    while board.count_hole() > holes && !board.is_game_over() {
+        let mut children = board.children();
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        board = children.swap_remove((state >> 33) as usize % children.len());
+    }
+    board
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::position::Position;
+
+    /// Published perft numbers of reversi from the initial position.
+    const PERFT: [u64; 11] = [
+        1, 4, 12, 56, 244, 1396, 8200, 55092, 390216, 3005288, 24571284,
+    ];
+
+    #[test]
+    fn perft_up_to_depth_9() {
+        let board = Board::new();
+
+        for (depth, &count) in PERFT.iter().enumerate().take(10) {
+            assert_eq!(board.perft(depth as u32), count, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn perft_depth_10() {
+        assert_eq!(Board::new().perft(10), PERFT[10]);
+    }
+
+    fn check_legal_moves(board: &Board, depth: u32) {
+        let moves = board.legal_moves();
+
+        for i in 0..64 {
+            let mov = 1 << i;
+            let legal = board.reversible_stones(mov) != 0;
+            assert_eq!(moves & mov != 0, legal, "{}\n{:?} {}", board, board.turn, i);
+        }
+        if depth > 0 {
+            for child in board.children() {
+                check_legal_moves(&child, depth - 1);
+            }
+        }
+    }
+
+    fn check_make_unmake(board: &mut Board, depth: u32) {
+        let children = board.children();
+        let original = board.clone();
+        let mut moves = board.legal_move_iter();
+
+        if board.legal_moves() == 0 {
+            if let Some(child) = children.first() {
+                let undo = board.make_pass();
+                assert_eq!(board, child);
+                check_make_unmake(board, depth);
+                board.unmake_move(undo);
+            }
+        } else {
+            for child in &children {
+                let undo = board.make_move(moves.next().unwrap());
+                assert_eq!(board, child);
+                if depth > 0 {
+                    check_make_unmake(board, depth - 1);
+                }
+                board.unmake_move(undo);
+                assert_eq!(*board, original);
+            }
+        }
+        assert_eq!(moves.next(), None);
+    }
+
+    #[test]
+    fn make_move_and_unmake_move_agree_with_children() {
+        check_make_unmake(&mut Board::new(), 5);
+    }
+
+    #[test]
+    fn legal_moves_agree_with_reversible_stones() {
+        check_legal_moves(&Board::new(), 6);
+    }
+
+    #[test]
+    fn symmetric_boards_share_canonical_form() {
+        let mut board = Board::new();
+        for mov in ["f5", "d6", "c3"].iter() {
+            board.put_stone(Position::from_str(mov).unwrap().as_bits());
+        }
+        let (canonical, symmetry) = board.canonical();
+
+        assert_eq!(board.transform(symmetry), canonical);
+        assert_eq!(canonical.transform(symmetry.inverse()), board);
+
+        for &other in Symmetry::ALL.iter() {
+            assert_eq!(board.transform(other).canonical().0, canonical);
+        }
+    }
+
+    #[test]
+    fn notation_round_trip() {
+        let mut board = Board::new();
+        board.put_stone(Position::from_str("f5").unwrap().as_bits());
+        let notation = board.to_notation();
+
+        assert_eq!(
+            notation,
+            "---------------------------OX------XXX-------------------------- O"
+        );
+        assert_eq!(notation.parse(), Ok(board.clone()));
+        assert_eq!(notation.to_lowercase().replace('-', ".").parse(), Ok(board));
+        assert!("XO O".parse::<Board>().is_err());
+        assert!(notation.replace('O', "?").parse::<Board>().is_err());
+    }
+}
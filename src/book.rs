@@ -0,0 +1,337 @@
+//! Opening book.
+//!
+//! Maps positions to candidate moves with their scores.  Positions
+//! are folded by the 8 rotations and reflections of the board, so an
+//! entry also covers every symmetric copy of its position.
+//!
+//! Book files are text, one candidate move in a line:
+//!
+//! ```text
+//! <bits of player to move in hex> <bits of opponent in hex> <move> <score>
+//! ```
+//!
+//! where the position and the move are in the canonical orientation,
+//! and the score is seen from the player to move.
+//!
+
+use crate::bitboard::{BitBoard, Move, Symmetry};
+use crate::board::Board;
+use crate::minimax;
+use crate::player::Action;
+use crate::position::Position;
+use crate::transcript::{self, TranscriptError};
+use crate::transposition::TranspositionTable;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A candidate move in the book.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BookMove {
+    pub mov: Move,
+    /// Score of the move seen from the player to move.
+    pub score: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    entries: HashMap<(BitBoard, BitBoard), Vec<BookMove>>,
+}
+
+impl Book {
+    pub fn new() -> Self {
+        Book {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Candidate moves for `board`, best first.
+    pub fn lookup(&self, board: &Board) -> Vec<BookMove> {
+        let (key, symmetry) = canonical_key(board);
+        let inverse = symmetry.inverse();
+
+        let mut moves: Vec<BookMove> = self
+            .entries
+            .get(&key)
+            .map(|moves| {
+                moves
+                    .iter()
+                    .map(|m| BookMove {
+                        mov: inverse.apply(m.mov),
+                        score: m.score,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        moves.sort_by_key(|m| -m.score);
+        moves
+    }
+
+    /// The best candidate move for `board`.
+    pub fn best_move(&self, board: &Board) -> Option<BookMove> {
+        self.lookup(board).into_iter().next()
+    }
+
+    /// Add `mov` with `score` at `board`, replacing the old score.
+    pub fn insert(&mut self, board: &Board, mov: Move, score: i32) {
+        let (key, symmetry) = canonical_key(board);
+        self.insert_by_key(key, symmetry.apply(mov), score);
+    }
+
+    /// `insert` by `canonical_key` and the move seen from it.
+    fn insert_by_key(&mut self, key: (BitBoard, BitBoard), mov: Move, score: i32) {
+        let moves = self.entries.entry(key).or_default();
+
+        match moves.iter_mut().find(|m| m.mov == mov) {
+            Some(m) => m.score = score,
+            None => moves.push(BookMove { mov, score }),
+        }
+    }
+
+    fn contains(&self, board: &Board, mov: Move) -> bool {
+        self.lookup(board).iter().any(|m| m.mov == mov)
+    }
+
+    ////////////////////////////////////////////////////////////////
+    // Builders
+
+    /// Add the moves of a game `transcript` (such as "f5d6c3") within
+    /// the first `plies`, scored by search of `depth`.
+    ///
+    /// The whole transcript is checked before any move is added.
+    pub fn add_transcript(
+        &mut self,
+        transcript: &str,
+        plies: u32,
+        depth: i32,
+    ) -> Result<(), TranscriptError> {
+        let game = transcript::parse(transcript)?;
+        let mut board = Board::new();
+        let mut tt = TranspositionTable::default();
+        let mut ply = 0;
+
+        for history in game.history() {
+            match history.action {
+                Action::Move(_) if ply >= plies => break,
+                Action::Move(pos) => {
+                    let mov = pos.as_bits();
+                    if !self.contains(&board, mov) {
+                        self.insert(&board, mov, score_move(&board, mov, depth, &mut tt));
+                    }
+                    board.put_stone(mov);
+                    ply += 1;
+                }
+                Action::Pass => {
+                    board.pass();
+                }
+                Action::GiveUp | Action::Undo | Action::Redo => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Add every move of every position within the first `plies`,
+    /// scored by search of `depth`.
+    pub fn expand(&mut self, plies: u32, depth: i32) {
+        let mut positions = vec![Board::new()];
+        let mut tt = TranspositionTable::default();
+
+        for _ in 0..plies {
+            let mut next: HashMap<(BitBoard, BitBoard), Board> = HashMap::new();
+
+            for board in &positions {
+                for mov in board.legal_move_iter() {
+                    if !self.contains(board, mov) {
+                        self.insert(board, mov, score_move(board, mov, depth, &mut tt));
+                    }
+                    let mut child = board.clone();
+                    child.put_stone(mov);
+                    if child.legal_moves() == 0 {
+                        child.pass();
+                    }
+                    next.entry(canonical_key(&child).0).or_insert(child);
+                }
+            }
+            positions = next.into_values().collect();
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////
+    // Files
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Read entries from `reader`; a move listed twice for the same
+    /// position has the later score, as by `insert`.
+    pub fn read_from(reader: impl BufRead) -> io::Result<Self> {
+        let mut book = Self::new();
+
+        for (lineno, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, book_move) = parse_line(&line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid book entry at line {}", lineno + 1),
+                )
+            })?;
+            book.insert_by_key(key, book_move.mov, book_move.score);
+        }
+        Ok(book)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Write entries sorted by position, so that files are stable.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut keys: Vec<_> = self.entries.keys().collect();
+        keys.sort();
+
+        for key in keys {
+            for m in &self.entries[key] {
+                writeln!(
+                    writer,
+                    "{:016x} {:016x} {} {}",
+                    key.0,
+                    key.1,
+                    Position::new(m.mov),
+                    m.score
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Score of `mov` at `board` seen from the player to move.
+fn score_move(board: &Board, mov: Move, depth: i32, tt: &mut TranspositionTable) -> i32 {
+    let mut child = board.clone();
+    child.put_stone(mov);
+    minimax::minimax_with_table(&child, board.turn, depth, tt)
+}
+
+fn parse_line(line: &str) -> Option<((BitBoard, BitBoard), BookMove)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    if words.len() != 4 {
+        return None;
+    }
+    let me = u64::from_str_radix(words[0], 16).ok()?;
+    let opponent = u64::from_str_radix(words[1], 16).ok()?;
+    let mov = Position::from_str(words[2]).ok()?.as_bits();
+    let score = words[3].parse().ok()?;
+
+    Some(((me, opponent), BookMove { mov, score }))
+}
+
+/// The smallest (player to move, opponent) pair among the 8 symmetric
+/// copies of `board`, and the symmetry which makes it.
+///
+/// Unlike `Board::canonical` ordered by (black, white), this is the
+/// order of book files.
+fn canonical_key(board: &Board) -> ((BitBoard, BitBoard), Symmetry) {
+    let (me, opponent) = if board.is_black_turn() {
+        (board.black, board.white)
+    } else {
+        (board.white, board.black)
+    };
+
+    Symmetry::ALL
+        .iter()
+        .map(|&symmetry| ((symmetry.apply(me), symmetry.apply(opponent)), symmetry))
+        .min_by_key(|&(key, _)| key)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn symmetric_positions_share_entries() {
+        let mut book = Book::new();
+        let mut board = Board::new();
+        let f5 = Position::from_str("f5").unwrap().as_bits();
+        let e6 = Position::from_str("e6").unwrap().as_bits();
+
+        book.insert(&board, f5, 3);
+        // all first moves are symmetric.
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.lookup(&board).len(), 1);
+
+        board.put_stone(f5);
+        book.insert(&board, Position::from_str("d6").unwrap().as_bits(), 5);
+
+        // e6 instead of f5 is the transposed copy; d6 turns into f4.
+        let mut transposed = Board::new();
+        transposed.put_stone(e6);
+        let f4 = Position::from_str("f4").unwrap().as_bits();
+        assert_eq!(
+            book.best_move(&transposed),
+            Some(BookMove { mov: f4, score: 5 })
+        );
+    }
+
+    #[test]
+    fn book_file_round_trip() {
+        let mut book = Book::new();
+        book.add_transcript("f5d6c3d3c4", 4, 1).unwrap();
+        assert!(book.add_transcript("f5f5", 4, 1).is_err());
+
+        let mut text = Vec::new();
+        book.write_to(&mut text).unwrap();
+        let loaded = Book::read_from(&text[..]).unwrap();
+
+        let mut board = Board::new();
+        board.put_stone(Position::from_str("f5").unwrap().as_bits());
+        assert_eq!(loaded.lookup(&board), book.lookup(&board));
+        assert_eq!(loaded.len(), 4);
+    }
+
+    #[test]
+    fn book_lines_put_the_player_to_move_first_and_round_trip() {
+        // white to move after f5 with 1 disc, then black's 4 discs.
+        let line = "0000000008000000 0000003810000000 e3 5\n";
+        let book = Book::read_from(line.as_bytes()).unwrap();
+
+        let mut board = Board::new();
+        board.put_stone(Position::from_str("f5").unwrap().as_bits());
+        let d6 = Position::from_str("d6").unwrap().as_bits();
+        assert_eq!(book.best_move(&board), Some(BookMove { mov: d6, score: 5 }));
+
+        let mut text = Vec::new();
+        book.write_to(&mut text).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), line);
+    }
+
+    #[test]
+    fn later_book_lines_replace_scores() {
+        let text = "0000000008000000 0000003810000000 e3 5\n\
+                    0000000008000000 0000003810000000 e3 -2\n";
+        let book = Book::read_from(text.as_bytes()).unwrap();
+
+        let mut board = Board::new();
+        board.put_stone(Position::from_str("f5").unwrap().as_bits());
+        let d6 = Position::from_str("d6").unwrap().as_bits();
+        assert_eq!(book.lookup(&board), vec![BookMove { mov: d6, score: -2 }]);
+    }
+}
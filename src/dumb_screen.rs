@@ -1,5 +1,6 @@
 use crate::board::Turn;
 use crate::position::*;
+use crate::screen::Screen;
 use crate::ui_board::{Color, UiBoard};
 
 pub struct DumbScreen {
@@ -15,13 +16,6 @@ impl DumbScreen {
         }
     }
 
-    pub fn update_screen(&self, board: &UiBoard) {
-        if !self.dumb {
-            Self::clear_screen();
-        }
-        self.print_board(board);
-    }
-
     fn clear_screen() {
         print!("\x1b[2J");
         Self::locate(1, 1);
@@ -70,3 +64,16 @@ impl DumbScreen {
         );
     }
 }
+
+impl Screen for DumbScreen {
+    fn update_screen(&self, board: &UiBoard) {
+        if !self.dumb {
+            Self::clear_screen();
+        }
+        self.print_board(board);
+    }
+
+    fn message(&self, message: &str) {
+        println!("{}", message);
+    }
+}
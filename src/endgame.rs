@@ -0,0 +1,337 @@
+//! Exact endgame solver.
+//!
+//! Reads the game out to the end and returns the final disc
+//! differential (or only win/loss/draw, which is faster) instead of a
+//! heuristic score.
+//!
+//! Moves are ordered:
+//! * fastest-first: moves leaving the opponent fewer replies first,
+//! * parity: moves into regions with an odd number of holes first.
+//!
+
+use crate::bitboard::{self, BitBoard, Move, MoveIter, Moves};
+use crate::board::Board;
+use std::fmt;
+
+/// Larger than any final disc differential.
+const INFINITY: i32 = 65;
+
+/// Use fastest-first ordering while more holes than this remain,
+/// cheaper parity ordering below.
+const FASTEST_FIRST_HOLES: u32 = 7;
+
+/// Most holes `check_holes` lets through.  Every 2 more holes take
+/// about 4 times longer; 24 take minutes, and 44 would never end.
+pub const MAX_HOLES: u32 = 24;
+
+/// a1-d4, e1-h4, a5-d8 and e5-h8.
+const QUADRANTS: [BitBoard; 4] = [
+    0xf0f0_f0f0_0000_0000,
+    0x0f0f_0f0f_0000_0000,
+    0x0000_0000_f0f0_f0f0,
+    0x0000_0000_0f0f_0f0f,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SolveMode {
+    /// Compute the exact final disc differential.
+    Exact,
+    /// Only tell win, loss or draw.
+    WinLossDraw,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Win => write!(f, "win")?,
+            Outcome::Loss => write!(f, "loss")?,
+            Outcome::Draw => write!(f, "draw")?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// Best move for the player to move; `None` if they have to pass
+    /// or the game is over.
+    pub best_move: Option<Move>,
+    /// Final disc differential seen from the player to move.
+    /// In `SolveMode::WinLossDraw`, only its sign is meaningful.
+    pub score: i32,
+    /// Line of best play to the end of the game, from `best_move`.
+    /// Passes are omitted.  In `SolveMode::WinLossDraw`, it's only a
+    /// line to the same outcome, and may stop short of the end.
+    pub pv: Vec<Move>,
+    /// Number of searched nodes.
+    pub nodes: u64,
+}
+
+impl Solution {
+    pub fn outcome(&self) -> Outcome {
+        match self.score {
+            s if s > 0 => Outcome::Win,
+            s if s < 0 => Outcome::Loss,
+            _ => Outcome::Draw,
+        }
+    }
+}
+
+/// Refuse `board` with more holes than `MAX_HOLES`, which `solve`
+/// wouldn't finish in reasonable time.
+pub fn check_holes(board: &Board) -> Result<(), String> {
+    let holes = board.count_hole();
+
+    if holes > MAX_HOLES {
+        return Err(format!(
+            "{} empties are too many to solve; at most {}",
+            holes, MAX_HOLES
+        ));
+    }
+    Ok(())
+}
+
+/// Solve `board` for the player to move.
+pub fn solve(board: &Board, mode: SolveMode) -> Solution {
+    let (mut alpha, beta) = match mode {
+        SolveMode::Exact => (-INFINITY, INFINITY),
+        SolveMode::WinLossDraw => (-1, 1),
+    };
+    let mut board = board.clone();
+    let mut solver = Solver {
+        nodes: 1,
+        pv: Vec::new(),
+    };
+    let moves = board.legal_moves();
+
+    if moves == 0 {
+        let score = -solver.pass(&mut board, -beta, -alpha, 0);
+        return Solution {
+            best_move: None,
+            score,
+            pv: solver.pv.swap_remove(0),
+            nodes: solver.nodes,
+        };
+    }
+
+    let mut list = MoveList::new();
+    let mut best_score = -INFINITY;
+    let mut best_move = None;
+
+    for &mov in list.order(&board, moves) {
+        let undo = board.make_move(mov);
+        let score = -solver.negamax(&mut board, -beta, -alpha, 1);
+        board.unmake_move(undo);
+
+        if score > best_score {
+            best_score = score;
+            best_move = Some(mov);
+            solver.update_pv(0, mov);
+            alpha = alpha.max(score);
+            if score >= beta {
+                break;
+            }
+        }
+    }
+    Solution {
+        best_move,
+        score: best_score,
+        pv: solver.pv.swap_remove(0),
+        nodes: solver.nodes,
+    }
+}
+
+struct Solver {
+    nodes: u64,
+    /// Principal variation of each ply: `pv[ply]` is the line of the
+    /// last searched node at `ply`.
+    pv: Vec<Vec<Move>>,
+}
+
+impl Solver {
+    /// Make `pv[ply]` `mov` (0 for pass) followed by `pv[ply + 1]`.
+    fn update_pv(&mut self, ply: usize, mov: Move) {
+        if self.pv.len() < ply + 2 {
+            self.pv.resize(ply + 2, Vec::new());
+        }
+        let (lines, rest) = self.pv.split_at_mut(ply + 1);
+        let line = &mut lines[ply];
+
+        line.clear();
+        if mov != 0 {
+            line.push(mov);
+        }
+        line.extend_from_slice(&rest[0]);
+    }
+
+    /// Fail-soft nega-max alpha-beta to the end of the game.
+    fn negamax(&mut self, board: &mut Board, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.nodes += 1;
+
+        if self.pv.len() <= ply {
+            self.pv.resize(ply + 1, Vec::new());
+        }
+        self.pv[ply].clear();
+
+        let moves = board.legal_moves();
+
+        if moves == 0 {
+            return -self.pass(board, -beta, -alpha, ply);
+        }
+
+        let mut list = MoveList::new();
+        let mut best_score = -INFINITY;
+
+        for &mov in list.order(board, moves) {
+            let undo = board.make_move(mov);
+            let score = -self.negamax(board, -beta, -alpha, ply + 1);
+            board.unmake_move(undo);
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    self.update_pv(ply, mov);
+                }
+                alpha = alpha.max(score);
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+        best_score
+    }
+
+    /// Score after the player to move at `board` of `ply` passes, seen
+    /// from the opponent; the game is over if neither can move.
+    fn pass(&mut self, board: &mut Board, alpha: i32, beta: i32, ply: usize) -> i32 {
+        let undo = board.make_pass();
+        let score = if board.legal_moves() == 0 {
+            // the game is over; nothing follows.
+            if self.pv.len() <= ply + 1 {
+                self.pv.resize(ply + 2, Vec::new());
+            }
+            self.pv[ply + 1].clear();
+            board.final_score(board.turn)
+        } else {
+            self.negamax(board, alpha, beta, ply + 1)
+        };
+        board.unmake_move(undo);
+        self.update_pv(ply, 0);
+        score
+    }
+}
+
+/// Squares in quadrants which have an odd number of holes.
+fn odd_region(holes: BitBoard) -> BitBoard {
+    QUADRANTS
+        .iter()
+        .filter(|&&q| (holes & q).count_ones() % 2 == 1)
+        .fold(0, |region, &q| region | q)
+}
+
+/// Ordered moves of a node, kept on the stack.
+struct MoveList {
+    moves: [(u32, Move); 64],
+}
+
+impl MoveList {
+    fn new() -> Self {
+        MoveList {
+            moves: [(0, 0); 64],
+        }
+    }
+
+    fn order(&mut self, board: &Board, moves: Moves) -> impl Iterator<Item = &Move> {
+        let holes = !(board.black | board.white);
+        let odd = odd_region(holes);
+        let fastest_first = holes.count_ones() > FASTEST_FIRST_HOLES;
+        let (me, opponent) = if board.is_black_turn() {
+            (board.black, board.white)
+        } else {
+            (board.white, board.black)
+        };
+        let mut n = 0;
+
+        for mov in MoveIter::new(moves) {
+            // parity: moves into odd regions first.
+            let mut key = (mov & odd == 0) as u32;
+
+            if fastest_first {
+                // fastest-first: opponent's mobility after the move.
+                let flipped = bitboard::reversible_stones(me, opponent, mov);
+                let mobility = bitboard::legal_moves(opponent & !flipped, me | mov | flipped);
+                key += mobility.count_ones() * 2;
+            }
+            self.moves[n] = (key, mov);
+            n += 1;
+        }
+        self.moves[..n].sort_by_key(|&(key, _)| key);
+        self.moves[..n].iter().map(|(_, mov)| mov)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::midgame_board;
+    use crate::transcript;
+
+    fn full_negamax(board: &Board) -> i32 {
+        let children = board.children();
+
+        if children.is_empty() {
+            return board.final_score(board.turn);
+        }
+        children
+            .iter()
+            .map(|child| -full_negamax(child))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn solve_agrees_with_full_search() {
+        for seed in 0..8 {
+            let board = midgame_board(seed, 8);
+            let expected = full_negamax(&board);
+            let exact = solve(&board, SolveMode::Exact);
+            let wld = solve(&board, SolveMode::WinLossDraw);
+
+            assert_eq!(exact.score, expected);
+            assert_eq!(wld.outcome(), exact.outcome());
+
+            if let Some(mov) = exact.best_move {
+                let mut child = board.clone();
+                child.put_stone(mov);
+                assert_eq!(-solve(&child, SolveMode::Exact).score, expected);
+            }
+
+            assert_eq!(exact.pv.first().copied(), exact.best_move);
+            let mut end = board.clone();
+            for &mov in &exact.pv {
+                if end.legal_moves() == 0 {
+                    end.pass();
+                }
+                end.put_stone(mov);
+            }
+            assert!(end.is_game_over());
+            assert_eq!(end.final_score(board.turn), expected);
+        }
+    }
+
+    #[test]
+    fn check_holes_refuses_midgames() {
+        let board = transcript::parse("f5d6c3d3c4f4f6f3e6e7d7g6").unwrap();
+        let err = check_holes(board.raw_board()).unwrap_err();
+
+        assert!(err.contains("48 empties"), "{}", err);
+        assert!(check_holes(&midgame_board(0, MAX_HOLES)).is_ok());
+    }
+}
@@ -0,0 +1,484 @@
+//! Evaluation functions used at leaves of the game tree search.
+//!
+//! * `TableEvaluator`: the hand-written square table of
+//!   `Board::eval_score`, used by default.
+//! * `NoisyEvaluator`: adds random noise to another evaluator, to
+//!   weaken the player on purpose.
+//! * `PatternEvaluator`: sums weights of edge, corner, diagonal and
+//!   other line patterns for each game phase.  Weights are loaded from
+//!   a text file, so that stronger evaluations can ship without
+//!   recompiling the search.
+//!
+
+use crate::board::{Board, Turn};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Evaluators are `Sync`, so that threads of a parallel search can
+/// share one.
+pub trait Evaluator: Sync {
+    /// Heuristic score of `board` seen from `turn`.
+    fn evaluate(&self, board: &Board, turn: Turn) -> i32;
+
+    /// Score of a finished game seen from `turn`, in the same unit as
+    /// `evaluate`.
+    fn final_score(&self, board: &Board, turn: Turn) -> i32 {
+        board.final_score(turn)
+    }
+}
+
+/// The hand-written square table with corner overrides.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TableEvaluator;
+
+impl Evaluator for TableEvaluator {
+    fn evaluate(&self, board: &Board, turn: Turn) -> i32 {
+        // the table counts discs of a full board, which is finished.
+        if board.count_hole() == 0 {
+            return self.final_score(board, turn);
+        }
+        board.eval_score(turn)
+    }
+
+    fn final_score(&self, board: &Board, turn: Turn) -> i32 {
+        board.final_score(turn) * Self::SCALE
+    }
+}
+
+impl TableEvaluator {
+    /// Points per disc of a finished game, so that a win by a disc
+    /// outscores any position of the table (corners are 30 points).
+    pub const SCALE: i32 = 1000;
+}
+
+/// Adds pseudo-random noise of up to `amplitude` to `inner`.
+///
+/// The noise is fixed for each position and `salt`, so that the
+/// search sees a consistent score for a position reached twice.
+pub struct NoisyEvaluator<'a> {
+    inner: &'a dyn Evaluator,
+    amplitude: i32,
+    salt: u64,
+}
+
+impl<'a> NoisyEvaluator<'a> {
+    pub fn new(inner: &'a dyn Evaluator, amplitude: i32, salt: u64) -> Self {
+        NoisyEvaluator {
+            inner,
+            amplitude: amplitude.max(0),
+            salt,
+        }
+    }
+
+    /// Noise for black in `-amplitude..=amplitude`.
+    fn noise(&self, board: &Board) -> i32 {
+        // SplitMix64 finalizer over both bitboards.
+        let mut x = board.black.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ board
+                .white
+                .rotate_left(32)
+                .wrapping_mul(0xbf58_476d_1ce4_e5b9)
+            ^ self.salt;
+        x ^= x >> 31;
+        x = x.wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^= x >> 29;
+
+        (x % (2 * self.amplitude as u64 + 1)) as i32 - self.amplitude
+    }
+}
+
+impl<'a> Evaluator for NoisyEvaluator<'a> {
+    fn evaluate(&self, board: &Board, turn: Turn) -> i32 {
+        let noise = if turn.is_black() {
+            self.noise(board)
+        } else {
+            -self.noise(board)
+        };
+        self.inner.evaluate(board, turn) + noise
+    }
+
+    fn final_score(&self, board: &Board, turn: Turn) -> i32 {
+        self.inner.final_score(board, turn)
+    }
+}
+
+////////////////////////////////////////////////////////////////
+// PatternEvaluator
+
+/// Squares of each pattern as (x, y).  Each pattern is also applied to
+/// its rotated and mirrored copies, which share the same weights.
+const PATTERNS: [&[(i32, i32)]; 10] = [
+    // edge
+    &[
+        (0, 0),
+        (1, 0),
+        (2, 0),
+        (3, 0),
+        (4, 0),
+        (5, 0),
+        (6, 0),
+        (7, 0),
+    ],
+    // corner 3x3
+    &[
+        (0, 0),
+        (1, 0),
+        (2, 0),
+        (0, 1),
+        (1, 1),
+        (2, 1),
+        (0, 2),
+        (1, 2),
+        (2, 2),
+    ],
+    // second, third and fourth lines
+    &[
+        (0, 1),
+        (1, 1),
+        (2, 1),
+        (3, 1),
+        (4, 1),
+        (5, 1),
+        (6, 1),
+        (7, 1),
+    ],
+    &[
+        (0, 2),
+        (1, 2),
+        (2, 2),
+        (3, 2),
+        (4, 2),
+        (5, 2),
+        (6, 2),
+        (7, 2),
+    ],
+    &[
+        (0, 3),
+        (1, 3),
+        (2, 3),
+        (3, 3),
+        (4, 3),
+        (5, 3),
+        (6, 3),
+        (7, 3),
+    ],
+    // diagonals of length 8 to 4
+    &[
+        (0, 0),
+        (1, 1),
+        (2, 2),
+        (3, 3),
+        (4, 4),
+        (5, 5),
+        (6, 6),
+        (7, 7),
+    ],
+    &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 6), (6, 7)],
+    &[(0, 2), (1, 3), (2, 4), (3, 5), (4, 6), (5, 7)],
+    &[(0, 3), (1, 4), (2, 5), (3, 6), (4, 7)],
+    &[(0, 4), (1, 5), (2, 6), (3, 7)],
+];
+
+/// Number of game phases, split by the number of stones.
+const PHASES: usize = 6;
+
+/// First line of weights files.
+const HEADER: &str = "flipout-pattern-weights 1";
+
+/// A copy of a pattern placed on the board.
+#[derive(Debug, Clone)]
+struct Instance {
+    /// Offset of the weights of the pattern in a phase.
+    offset: usize,
+    /// Bits of the squares; the first square is the most significant
+    /// ternary digit of the index.
+    squares: Vec<u64>,
+}
+
+/// Sums pattern weights of the game phase.
+///
+/// Each square of a pattern is empty (0), the evaluated player's (1) or
+/// the opponent's (2), and the ternary number of the squares indexes
+/// the weight.  Weights are in 1/`SCALE` discs.
+#[derive(Debug, Clone)]
+pub struct PatternEvaluator {
+    instances: Vec<Instance>,
+    /// `PHASES` x (bias + weights of all patterns)
+    weights: Vec<i32>,
+}
+
+impl Default for PatternEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evaluator for PatternEvaluator {
+    fn evaluate(&self, board: &Board, turn: Turn) -> i32 {
+        let base = Self::phase(board) * Self::weights_per_phase();
+        let mut score = 0;
+
+        self.features(board, turn, |index| score += self.weights[base + index]);
+        score
+    }
+
+    fn final_score(&self, board: &Board, turn: Turn) -> i32 {
+        board.final_score(turn) * Self::SCALE
+    }
+}
+
+impl PatternEvaluator {
+    /// Weights per disc.
+    pub const SCALE: i32 = 64;
+
+    /// Create evaluator with all weights 0.
+    pub fn new() -> Self {
+        let mut instances = Vec::new();
+        let mut offset = 1; // bias comes first
+
+        for pattern in PATTERNS.iter() {
+            let mut sets: Vec<u64> = Vec::new();
+
+            for symmetry in 0..8 {
+                let squares: Vec<u64> = pattern
+                    .iter()
+                    .map(|&(x, y)| {
+                        let (x, y) = transform(symmetry, x, y);
+                        (1 << 63) >> (y * 8 + x)
+                    })
+                    .collect();
+                let set = squares.iter().fold(0, |set, sq| set | sq);
+
+                // the same squares in another order are not a new copy.
+                if !sets.contains(&set) {
+                    sets.push(set);
+                    instances.push(Instance { offset, squares });
+                }
+            }
+            offset += 3_usize.pow(pattern.len() as u32);
+        }
+
+        PatternEvaluator {
+            instances,
+            weights: vec![0; PHASES * offset],
+        }
+    }
+
+    /// Load weights from a file written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Read weights written by `write_to`.
+    pub fn read_from(reader: impl BufRead) -> io::Result<Self> {
+        let mut evaluator = Self::new();
+        let mut lines = reader.lines();
+        let mut count = 0;
+
+        match lines.next() {
+            Some(Ok(ref line)) if line.trim() == HEADER => (),
+            _ => return Err(invalid_data("not a pattern weights file")),
+        }
+
+        for line in lines {
+            for word in line?.split_whitespace() {
+                let weight = word
+                    .parse()
+                    .map_err(|_| invalid_data(&format!("invalid weight '{}'", word)))?;
+                if count >= evaluator.weights.len() {
+                    return Err(invalid_data("too many weights"));
+                }
+                evaluator.weights[count] = weight;
+                count += 1;
+            }
+        }
+
+        if count != evaluator.weights.len() {
+            return Err(invalid_data("too few weights"));
+        }
+        Ok(evaluator)
+    }
+
+    /// Save weights to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Write weights as text: `HEADER` line, then one line for each
+    /// pattern (preceded by the bias) in each phase.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let per_phase = Self::weights_per_phase();
+        let mut boundaries = vec![1];
+
+        writeln!(writer, "{}", HEADER)?;
+
+        for pattern in PATTERNS.iter() {
+            let last = *boundaries.last().unwrap();
+            boundaries.push(last + 3_usize.pow(pattern.len() as u32));
+        }
+
+        for phase in self.weights.chunks(per_phase) {
+            writeln!(writer, "{}", phase[0])?;
+            for range in boundaries.windows(2) {
+                let line: Vec<String> = phase[range[0]..range[1]]
+                    .iter()
+                    .map(|w| w.to_string())
+                    .collect();
+                writeln!(writer, "{}", line.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Indices into all the weights of every feature which adds up to
+    /// the score of `board` seen from `turn`.
+    pub(crate) fn feature_indices(&self, board: &Board, turn: Turn) -> Vec<usize> {
+        let base = Self::phase(board) * Self::weights_per_phase();
+        let mut indices = Vec::with_capacity(self.instances.len() + 1);
+
+        self.features(board, turn, |index| indices.push(base + index));
+        indices
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [i32] {
+        &mut self.weights
+    }
+
+    /// Game phase of `board` in `0..PHASES`.
+    fn phase(board: &Board) -> usize {
+        (board.count_stone().saturating_sub(4) as usize / 10).min(PHASES - 1)
+    }
+
+    fn weights_per_phase() -> usize {
+        1 + PATTERNS
+            .iter()
+            .map(|p| 3_usize.pow(p.len() as u32))
+            .sum::<usize>()
+    }
+
+    /// Call `f` with the index of every weight (within the phase) which
+    /// adds up to the score of `board` seen from `turn`.
+    fn features(&self, board: &Board, turn: Turn, mut f: impl FnMut(usize)) {
+        let (me, opponent) = if turn.is_black() {
+            (board.black, board.white)
+        } else {
+            (board.white, board.black)
+        };
+
+        f(0); // bias
+
+        for instance in &self.instances {
+            let mut index = 0;
+
+            for &square in &instance.squares {
+                index *= 3;
+                if me & square != 0 {
+                    index += 1;
+                } else if opponent & square != 0 {
+                    index += 2;
+                }
+            }
+            f(instance.offset + index);
+        }
+    }
+}
+
+/// Apply one of 8 rotations and reflections to (x, y).
+fn transform(symmetry: u32, x: i32, y: i32) -> (i32, i32) {
+    let (x, y) = if symmetry & 1 != 0 {
+        (7 - x, y)
+    } else {
+        (x, y)
+    };
+    let (x, y) = if symmetry & 2 != 0 {
+        (x, 7 - y)
+    } else {
+        (x, y)
+    };
+    if symmetry & 4 != 0 {
+        (y, x)
+    } else {
+        (x, y)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn table_evaluator_ranks_wins_above_positions() {
+        // a win by 2 discs on the full board.
+        let won: Board = format!("{}{} X", "X".repeat(33), "O".repeat(31))
+            .parse()
+            .unwrap();
+        // black has every corner and its neighbours, but no win yet.
+        let mut rich: Board =
+            "XX----XX XX----XX -------- ---OX--- ---XO--- -------- XX----XX XX----XX X"
+                .parse()
+                .unwrap();
+        rich.turn = Turn::Black;
+
+        assert!(won.is_game_over());
+        assert_eq!(
+            TableEvaluator.final_score(&won, Turn::Black),
+            2 * TableEvaluator::SCALE
+        );
+        assert!(
+            TableEvaluator.final_score(&won, Turn::Black)
+                > TableEvaluator.evaluate(&rich, Turn::Black)
+        );
+        assert!(
+            -TableEvaluator.final_score(&won, Turn::White)
+                > TableEvaluator.evaluate(&rich, Turn::Black)
+        );
+    }
+
+    #[test]
+    fn weights_file_round_trip() {
+        let mut evaluator = PatternEvaluator::new();
+        for (i, weight) in evaluator.weights.iter_mut().enumerate() {
+            *weight = (i % 201) as i32 - 100;
+        }
+
+        let mut text = Vec::new();
+        evaluator.write_to(&mut text).unwrap();
+        let loaded = PatternEvaluator::read_from(&text[..]).unwrap();
+        assert_eq!(loaded.weights, evaluator.weights);
+
+        let board = Board::new();
+        assert_eq!(
+            loaded.evaluate(&board, Turn::Black),
+            evaluator.evaluate(&board, Turn::Black)
+        );
+
+        // fewer discs than the initial position, as from a position file.
+        let sparse: Board = format!("X{}O X", "-".repeat(62)).parse().unwrap();
+        assert_eq!(PatternEvaluator::phase(&sparse), 0);
+
+        let truncated = &text[..text.len() / 2];
+        assert!(PatternEvaluator::read_from(truncated).is_err());
+    }
+
+    #[test]
+    fn every_pattern_has_its_copies() {
+        let evaluator = PatternEvaluator::new();
+        let mut copies = Vec::new();
+
+        for instance in &evaluator.instances {
+            match copies.last_mut() {
+                Some((offset, count)) if *offset == instance.offset => *count += 1,
+                _ => copies.push((instance.offset, 1)),
+            }
+        }
+        let counts: Vec<i32> = copies.iter().map(|&(_, count)| count).collect();
+        assert_eq!(counts, vec![4, 4, 4, 4, 4, 2, 4, 4, 4, 4]);
+    }
+}
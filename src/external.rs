@@ -0,0 +1,126 @@
+//! Player backed by an external engine process.
+//!
+//! The engine reads a position per line in the notation of
+//! `Board::to_notation`, such as:
+//!
+//! ```text
+//! ---------------------------OX------XXX-------------------------- O
+//! ```
+//!
+//! and answers a line with its move such as "d6", or "pass".  Any
+//! other answer, an illegal move, or a dead engine gives up the game.
+//!
+
+use crate::player::{Action, Player};
+use crate::position::Position;
+use crate::ui_board::UiBoard;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::str::FromStr;
+
+pub struct ExternalPlayer {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+}
+
+impl ExternalPlayer {
+    /// Start `program` with `args`.
+    pub fn spawn(program: &str, args: &[&str]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let input = child.stdin.take().unwrap();
+        let output = BufReader::new(child.stdout.take().unwrap());
+
+        Ok(ExternalPlayer {
+            child,
+            input,
+            output,
+        })
+    }
+
+    /// Send `board` and read the answer.
+    fn ask(&mut self, board: &UiBoard) -> io::Result<String> {
+        writeln!(self.input, "{}", board.raw_board().to_notation())?;
+        self.input.flush()?;
+
+        let mut line = String::new();
+        if self.output.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "engine exited",
+            ));
+        }
+        Ok(line.trim().to_lowercase())
+    }
+}
+
+impl Player for ExternalPlayer {
+    fn action(&mut self, board: &UiBoard) -> Action {
+        let answer = match self.ask(board) {
+            Ok(answer) => answer,
+            Err(err) => {
+                eprintln!("engine: {}", err);
+                return Action::GiveUp;
+            }
+        };
+
+        let action = match &*answer {
+            "pass" => Action::Pass,
+            _ => match Position::from_str(&answer) {
+                Ok(pos) => Action::Move(pos),
+                Err(_) => {
+                    eprintln!("engine: invalid answer '{}'", answer);
+                    return Action::GiveUp;
+                }
+            },
+        };
+
+        // asking again would get the same answer.
+        let legal = match action {
+            Action::Move(pos) => board.is_legal_move(pos),
+            _ => board.legal_moves().as_bits() == 0,
+        };
+        if !legal {
+            eprintln!("engine: illegal move '{}'", answer);
+            return Action::GiveUp;
+        }
+        action
+    }
+}
+
+impl Drop for ExternalPlayer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Engine by the shell answering `answer` to every position.
+    fn stub_engine(answer: &str) -> ExternalPlayer {
+        let script = format!("while read position; do echo {}; done", answer);
+        ExternalPlayer::spawn("sh", &["-c", &script]).unwrap()
+    }
+
+    #[test]
+    fn engine_answers_are_checked() {
+        let board = UiBoard::new();
+        let f5 = Position::from_str("f5").unwrap();
+
+        assert_eq!(stub_engine("F5").action(&board), Action::Move(f5));
+        assert_eq!(stub_engine("f5").action(&board), Action::Move(f5));
+        assert_eq!(stub_engine("a1").action(&board), Action::GiveUp);
+        assert_eq!(stub_engine("pass").action(&board), Action::GiveUp);
+        assert_eq!(stub_engine("resign").action(&board), Action::GiveUp);
+
+        let mut dead = ExternalPlayer::spawn("sh", &["-c", "exit 0"]).unwrap();
+        assert_eq!(dead.action(&board), Action::GiveUp);
+    }
+}
@@ -0,0 +1,182 @@
+//! Players built from spec strings such as "clever:level=expert,time=500".
+//!
+//! A spec is a player type and optional `KEY=VALUE` settings after ":".
+//!
+
+use crate::book::Book;
+use crate::evaluator::PatternEvaluator;
+use crate::external::ExternalPlayer;
+use crate::level::Level;
+use crate::mcts::{MctsPlayer, Playout};
+use crate::player::{CleverRobotPlayer, HumanPlayer, Player, RobotPlayer};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Description of specs for help messages.
+pub const SPEC_HELP: &str = r#"    human: You at the terminal
+    random[:seed=N]: Random moves
+    clever[:level=LEVEL,depth=N,time=MSEC,seed=N,book=FILE,weights=FILE,threads=N]:
+      Minimax search; level is beginner, easy, medium, hard (default) or expert
+    mcts[:iterations=N,time=MSEC,seed=N,exploration=X,playout=random|light]:
+      Monte Carlo tree search
+    engine:COMMAND [ARGS...]: External program which reads a position per
+      line (such as "---...--- X") and answers its move (such as "f5" or "pass")
+"#;
+
+/// Build a player from `spec`.
+pub fn player_from_spec(spec: &str) -> Result<Box<dyn Player>, String> {
+    let (kind, settings) = match spec.split_once(':') {
+        Some((kind, settings)) => (kind.trim(), settings.trim()),
+        None => (spec.trim(), ""),
+    };
+
+    if kind == "engine" {
+        let mut words = settings.split_whitespace();
+        let program = words.next().ok_or("engine needs a command")?;
+        let args: Vec<&str> = words.collect();
+        let engine = ExternalPlayer::spawn(program, &args)
+            .map_err(|err| format!("can't start {}: {}", program, err))?;
+        return Ok(Box::new(engine));
+    }
+
+    let settings = parse_settings(kind, settings)?;
+    let player: Box<dyn Player> = match kind {
+        "human" => {
+            check_keys(kind, &settings, &[])?;
+            Box::new(HumanPlayer::stdin())
+        }
+        "random" => {
+            check_keys(kind, &settings, &["seed"])?;
+            let mut robot = RobotPlayer::new();
+            for (key, value) in &settings {
+                if *key == "seed" {
+                    robot = robot.with_seed(parse_value(key, value)?);
+                }
+            }
+            Box::new(robot)
+        }
+        "clever" => Box::new(clever_from_settings(&settings)?),
+        "mcts" => Box::new(mcts_from_settings(&settings)?),
+        _ => return Err(format!("unknown player type '{}'", kind)),
+    };
+    Ok(player)
+}
+
+fn clever_from_settings(settings: &[(&str, &str)]) -> Result<CleverRobotPlayer, String> {
+    let keys = [
+        "level", "depth", "time", "seed", "book", "weights", "threads",
+    ];
+    check_keys("clever", settings, &keys)?;
+
+    // the level comes first, so that others override it.
+    let mut player = CleverRobotPlayer::new();
+    if let Some((key, value)) = settings.iter().find(|(key, _)| *key == "level") {
+        player = player.with_level(parse_value::<Level>(key, value)?);
+    }
+
+    for &(key, value) in settings {
+        player = match key {
+            "depth" => player.with_depth(parse_value(key, value)?),
+            "time" => player.with_time_budget(Duration::from_millis(parse_value(key, value)?)),
+            "seed" => player.with_seed(parse_value(key, value)?),
+            "book" => player.with_book(
+                Book::load(value).map_err(|err| format!("can't load {}: {}", value, err))?,
+            ),
+            "weights" => player.with_evaluator(Box::new(
+                PatternEvaluator::load(value)
+                    .map_err(|err| format!("can't load {}: {}", value, err))?,
+            )),
+            #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+            "threads" => player.with_threads(parse_value(key, value)?),
+            #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+            "threads" => return Err("threads needs 'parallel' feature".to_string()),
+            _ => player,
+        };
+    }
+    Ok(player)
+}
+
+fn mcts_from_settings(settings: &[(&str, &str)]) -> Result<MctsPlayer, String> {
+    let keys = ["iterations", "time", "seed", "exploration", "playout"];
+    check_keys("mcts", settings, &keys)?;
+    let mut player = MctsPlayer::new();
+
+    // a time budget removes the iteration limit, so it comes first.
+    if let Some((key, value)) = settings.iter().find(|(key, _)| *key == "time") {
+        player = player.with_time_budget(Duration::from_millis(parse_value(key, value)?));
+    }
+
+    for &(key, value) in settings {
+        player = match key {
+            "iterations" => player.with_iterations(parse_value(key, value)?),
+            "seed" => player.with_seed(parse_value(key, value)?),
+            "exploration" => player.with_exploration(parse_value(key, value)?),
+            "playout" => match value {
+                "random" => player.with_playout(Playout::Random),
+                "light" => player.with_playout(Playout::Light),
+                _ => return Err(format!("unknown playout '{}'", value)),
+            },
+            _ => player,
+        };
+    }
+    Ok(player)
+}
+
+/// Split "KEY=VALUE,..." of `kind` into pairs.
+fn parse_settings<'a>(kind: &str, settings: &'a str) -> Result<Vec<(&'a str, &'a str)>, String> {
+    settings
+        .split(',')
+        .map(str::trim)
+        .filter(|setting| !setting.is_empty())
+        .map(|setting| {
+            setting
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or(format!("'{}' of {} needs KEY=VALUE", setting, kind))
+        })
+        .collect()
+}
+
+fn check_keys(kind: &str, settings: &[(&str, &str)], keys: &[&str]) -> Result<(), String> {
+    match settings.iter().find(|(key, _)| !keys.contains(key)) {
+        Some((key, _)) => Err(format!("unknown setting '{}' for {}", key, kind)),
+        None => Ok(()),
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, key))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::player::Action;
+    use crate::ui_board::UiBoard;
+
+    #[test]
+    fn players_from_specs() {
+        let board = UiBoard::new();
+
+        for spec in &[
+            "random:seed=1",
+            "clever",
+            "clever:level=beginner,depth=2,seed=3",
+            "mcts:iterations=50,seed=1,playout=light",
+        ] {
+            let mut player = player_from_spec(spec).unwrap();
+            match player.action(&board) {
+                Action::Move(pos) => assert!(board.is_legal_move(pos), "{}", spec),
+                action => panic!("{}: {}", spec, action),
+            }
+        }
+
+        assert!(player_from_spec("wizard").is_err());
+        assert!(player_from_spec("clever:level=grandmaster").is_err());
+        assert!(player_from_spec("clever:color=red").is_err());
+        assert!(player_from_spec("mcts:iterations").is_err());
+        assert!(player_from_spec("engine:").is_err());
+    }
+}
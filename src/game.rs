@@ -1,4 +1,5 @@
 use crate::board::*;
+use crate::level::Level;
 use crate::player::*;
 use crate::position::*;
 use crate::ui_board::*;
@@ -11,6 +12,8 @@ pub struct Game {
     board: UiBoard,
     screen: WasmScreen,
     ai: Box<dyn Player>,
+    level: Level,
+    seed: Option<u64>,
 }
 
 impl Default for Game {
@@ -29,9 +32,37 @@ impl Game {
             board: ui,
             ai,
             screen,
+            level: Level::default(),
+            seed: None,
         }
     }
 
+    /// Create a game whose AI moves are reproducible by `seed`.
+    pub fn new_with_seed(seed: u32) -> Self {
+        let mut game = Self::new();
+        game.set_seed(seed);
+        game
+    }
+
+    /// Change the AI to `level` ("beginner", "easy", "medium", "hard"
+    /// or "expert"); return false for an unknown level.
+    pub fn set_level(&mut self, level: &str) -> bool {
+        match level.parse::<Level>() {
+            Ok(level) => {
+                self.level = level;
+                self.reset_ai();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Make the AI moves from now on reproducible by `seed`.
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = Some(seed as u64);
+        self.reset_ai();
+    }
+
     pub fn ui_move(&mut self, turn: Turn, x: i32, y: i32) -> bool {
         if let Some(pos) = Position::from_xy(x, y) {
             let action = Action::Move(pos);
@@ -64,6 +95,15 @@ impl Game {
         }
     }
 
+    /// Report of the search behind the last AI move, such as
+    /// "depth 6 score +120 nodes 5678 time 0.012s pv f5 d6 c3".
+    /// Empty if none.
+    pub fn ai_report(&self) -> String {
+        self.ai
+            .last_report()
+            .map_or(String::new(), |report| report.to_string())
+    }
+
     pub fn update_screen(&self) {
         let board = &self.board;
         self.screen
@@ -72,6 +112,15 @@ impl Game {
 }
 
 impl Game {
+    fn reset_ai(&mut self) {
+        let mut ai = CleverRobotPlayer::new().with_level(self.level);
+
+        if let Some(seed) = self.seed {
+            ai = ai.with_seed(seed);
+        }
+        self.ai = Box::new(ai);
+    }
+
     fn update(&mut self, turn: Turn, action: Action) -> Option<Turn> {
         if self.board.whatnow() != Some(turn) {
             return self.board.whatnow();
@@ -79,6 +128,16 @@ impl Game {
 
         match action {
             Action::GiveUp => (),
+            Action::Undo => {
+                if self.board.undo_until(turn).is_ok() {
+                    self.update_screen();
+                }
+            }
+            Action::Redo => {
+                if self.board.redo_until(turn).is_ok() {
+                    self.update_screen();
+                }
+            }
             Action::Pass => {
                 if self.board.pass().is_ok() {
                     self.update_screen();
@@ -0,0 +1,489 @@
+//! GGF (Generic Game Format) records, used by online reversi servers.
+//!
+//! A game looks like:
+//!
+//! ```text
+//! (;GM[Othello]PB[alice]PW[bob]RB[1800]RW[1750]TI[05:00]TY[8]RE[+4.000]
+//! BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]
+//! B[F5]W[D6/-1.50/2.31]B[C3//0.5];)
+//! ```
+//!
+//! `BO` is the starting board, where `*` is black and `O` is white, followed
+//! by the side to move.  Moves are `B[...]` or `W[...]` with an optional
+//! evaluation and time in seconds, and `PA` for a pass.  Any other tag is
+//! kept as metadata in its order.
+//!
+
+use crate::board::{Board, Turn};
+use crate::player::Action;
+use crate::position::Position;
+use crate::ui_board::UiBoard;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GgfMove {
+    pub turn: Turn,
+    /// `Action::Move` or `Action::Pass`.
+    pub action: Action,
+    /// Evaluation by the player, if recorded.
+    pub eval: Option<f64>,
+    /// Thinking time in seconds, if recorded.
+    pub time: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GgfGame {
+    /// Tags other than `BO`, `B` and `W`, such as `PB` and `RE`.
+    pub tags: Vec<(String, String)>,
+    /// Board before the first move.
+    pub start: Board,
+    pub moves: Vec<GgfMove>,
+}
+
+impl GgfGame {
+    /// Record of the game on `board` with `GM[Othello]`, and with the
+    /// result `RE` if the game is over.
+    pub fn from_ui_board(board: &UiBoard) -> Self {
+        let mut tags = vec![("GM".to_string(), "Othello".to_string())];
+        let moves = board
+            .history()
+            .iter()
+            .filter(|history| history.action != Action::GiveUp)
+            .map(|history| GgfMove {
+                turn: history.turn,
+                action: history.action,
+                eval: None,
+                time: None,
+            })
+            .collect();
+
+        if board.is_game_over() {
+            let score = board.raw_board().final_score(Turn::Black);
+            tags.push(("RE".to_string(), format!("{:+.3}", score as f64)));
+        }
+
+        GgfGame {
+            tags,
+            start: board.start_board().clone(),
+            moves,
+        }
+    }
+
+    /// Record of the game on `board` as `from_ui_board`, keeping the
+    /// other tags of `self` and the evaluations and times of the moves
+    /// which `board` still has from `self`.
+    pub fn updated(&self, board: &UiBoard) -> Self {
+        let fresh = Self::from_ui_board(board);
+        let mut game = GgfGame {
+            tags: self
+                .tags
+                .iter()
+                .filter(|(name, _)| name != "RE")
+                .cloned()
+                .collect(),
+            start: fresh.start,
+            moves: fresh.moves,
+        };
+
+        for (name, value) in &fresh.tags {
+            game.set_tag(name, value);
+        }
+        if game.start != self.start {
+            return game;
+        }
+
+        let mut old_moves = self.moves.iter().peekable();
+        for mov in game.moves.iter_mut() {
+            match old_moves.peek() {
+                Some(old) if old.turn == mov.turn && old.action == mov.action => {
+                    mov.eval = old.eval;
+                    mov.time = old.time;
+                    old_moves.next();
+                }
+                // passes may be left out of `self`.
+                _ if mov.action == Action::Pass => (),
+                _ => break,
+            }
+        }
+        game
+    }
+
+    /// Value of tag `name`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set tag `name` to `value`, replacing the old value if any.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Replay the moves from `start`, checking every move.
+    ///
+    /// Passes may be left out, since `UiBoard` passes by itself.
+    pub fn replay(&self) -> Result<UiBoard, String> {
+        let mut board = UiBoard::from_board(self.start.clone());
+        // history entries matched with the moves so far.
+        let mut matched = 0;
+
+        for (mov, ply) in self.moves.iter().zip(1..) {
+            let history = board.history();
+
+            if mov.action == Action::Pass {
+                match history.get(matched) {
+                    Some(h) if h.action == Action::Pass && h.turn == mov.turn => {
+                        matched += 1;
+                        continue;
+                    }
+                    _ => return Err(format!("move {}: pass while having a legal move", ply)),
+                }
+            }
+            if board.whatnow() != Some(mov.turn) {
+                return Err(format!("move {}: not {}'s turn", ply, mov.turn));
+            }
+            matched = history.len() + 1;
+
+            if let Action::Move(pos) = mov.action {
+                board
+                    .put_stone(pos)
+                    .map_err(|_| format!("move {}: {} is not a legal move", ply, pos))?;
+            }
+        }
+        Ok(board)
+    }
+}
+
+/// Read every game in `text`, skipping anything between games.
+pub fn parse_games(text: &str) -> Result<Vec<GgfGame>, String> {
+    let mut games = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("(;") {
+        let end = start
+            + game_length(&rest[start..])
+                .ok_or_else(|| format!("game {} is not closed", games.len() + 1))?;
+        let game = rest[start..end]
+            .parse()
+            .map_err(|err| format!("game {}: {}", games.len() + 1, err))?;
+
+        games.push(game);
+        rest = &rest[end..];
+    }
+    Ok(games)
+}
+
+impl FromStr for GgfGame {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let body = text
+            .trim()
+            .strip_prefix("(;")
+            .and_then(|s| s.strip_suffix(";)"))
+            .ok_or("game must be enclosed by '(;' and ';)'")?;
+        let mut game = GgfGame {
+            tags: Vec::new(),
+            start: Board::new(),
+            moves: Vec::new(),
+        };
+
+        for (name, value) in parse_tags(body)? {
+            match &*name {
+                "BO" => game.start = parse_board(&value)?,
+                "B" => game.moves.push(parse_move(Turn::Black, &value)?),
+                "W" => game.moves.push(parse_move(Turn::White, &value)?),
+                _ => game.tags.push((name, value)),
+            }
+        }
+        Ok(game)
+    }
+}
+
+impl fmt::Display for GgfGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(;")?;
+        for (name, value) in &self.tags {
+            write!(f, "{}[{}]", name, escape(value))?;
+        }
+        write!(f, "BO[{}]", format_board(&self.start))?;
+        for mov in &self.moves {
+            write!(f, "{}", mov)?;
+        }
+        write!(f, ";)")
+    }
+}
+
+impl fmt::Display for GgfMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let color = if self.turn.is_black() { "B" } else { "W" };
+        let square = match self.action {
+            Action::Move(pos) => pos.to_string().to_uppercase(),
+            _ => "PA".to_string(),
+        };
+
+        write!(f, "{}[{}", color, square)?;
+        match (self.eval, self.time) {
+            (None, None) => (),
+            (Some(eval), None) => write!(f, "/{}", eval)?,
+            (None, Some(time)) => write!(f, "//{}", time)?,
+            (Some(eval), Some(time)) => write!(f, "/{}/{}", eval, time)?,
+        }
+        write!(f, "]")
+    }
+}
+
+////////////////////////////////////////////////////////////////
+// Private
+
+/// Length of the game at the beginning of `text` up to the parenthesis
+/// closing it.  Tag values may have any character, such as ";)" in
+/// comments, and are skipped.
+fn game_length(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = text.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '[' => loop {
+                match chars.next()?.1 {
+                    ']' => break,
+                    '\\' => {
+                        chars.next()?;
+                    }
+                    _ => (),
+                }
+            },
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Split `body` into `NAME[value]` pairs, unescaping values.
+fn parse_tags(body: &str) -> Result<Vec<(String, String)>, String> {
+    let mut tags = Vec::new();
+    let mut chars = body.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Ok(tags);
+        }
+
+        let mut name = String::new();
+        for c in chars.by_ref() {
+            if c == '[' {
+                break;
+            }
+            name.push(c);
+        }
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!("invalid tag '{}'", name));
+        }
+
+        let mut value = String::new();
+        loop {
+            match chars.next() {
+                Some(']') => break,
+                Some('\\') => value.extend(chars.next()),
+                Some(c) => value.push(c),
+                None => return Err(format!("tag {} is not closed", name)),
+            }
+        }
+        tags.push((name, value));
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+/// Parse "8 <64 squares> <side to move>" of `BO`.
+fn parse_board(value: &str) -> Result<Board, String> {
+    let invalid = || format!("invalid board '{}'", value);
+    let chars: Vec<char> = value
+        .trim()
+        .strip_prefix('8')
+        .ok_or_else(invalid)?
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    if chars.len() != 65 {
+        return Err(invalid());
+    }
+
+    let mut board = Board {
+        black: 0,
+        white: 0,
+        turn: Turn::Black,
+    };
+    for (i, c) in chars[..64].iter().enumerate() {
+        let bit = (1 << 63) >> i;
+        match c {
+            '*' => board.black |= bit,
+            'O' => board.white |= bit,
+            '-' => (),
+            _ => return Err(invalid()),
+        }
+    }
+    board.turn = match chars[64] {
+        '*' => Turn::Black,
+        'O' => Turn::White,
+        _ => return Err(invalid()),
+    };
+    Ok(board)
+}
+
+fn format_board(board: &Board) -> String {
+    let mut text = "8".to_string();
+
+    for i in 0..64 {
+        if i % 8 == 0 {
+            text.push(' ');
+        }
+        let bit = (1 << 63) >> i;
+        text.push(if board.black & bit != 0 {
+            '*'
+        } else if board.white & bit != 0 {
+            'O'
+        } else {
+            '-'
+        });
+    }
+    text.push_str(if board.turn.is_black() { " *" } else { " O" });
+    text
+}
+
+/// Parse "F5", "F5/1.50", "F5//2.3" or "PA/0/1" of a move.
+fn parse_move(turn: Turn, value: &str) -> Result<GgfMove, String> {
+    let mut fields = value.split('/');
+    let square = fields.next().unwrap_or("").trim().to_lowercase();
+    let number = |field: Option<&str>| match field.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(field) => parse_seconds(field)
+            .map(Some)
+            .ok_or(format!("invalid number '{}' in move '{}'", field, value)),
+    };
+
+    let action = if square == "pa" {
+        Action::Pass
+    } else {
+        Position::from_str(&square)
+            .map(Action::Move)
+            .map_err(|_| format!("invalid move '{}'", value))?
+    };
+    Ok(GgfMove {
+        turn,
+        action,
+        eval: number(fields.next())?,
+        time: number(fields.next())?,
+    })
+}
+
+/// Parse a number, or a time such as "1:02.5" into seconds.
+fn parse_seconds(field: &str) -> Option<f64> {
+    field.split(':').try_fold(0.0, |sum, part| {
+        Some(sum * 60.0 + part.parse::<f64>().ok()?)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transcript;
+
+    #[test]
+    fn ggf_round_trip() {
+        let text = "(;GM[Othello]PC[NOIS]PB[alice]PW[bob \\] jr]RB[1800.5]RW[1750]\
+                    TI[05:00//02:00]TY[8]\
+                    BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]\
+                    B[F5]W[D6/-1.5/2.25]B[c3//1:02];)";
+        let game: GgfGame = text.parse().unwrap();
+
+        assert_eq!(game.tag("PW"), Some("bob ] jr"));
+        assert_eq!(game.tag("TI"), Some("05:00//02:00"));
+        assert_eq!(game.moves[1].eval, Some(-1.5));
+        assert_eq!(game.moves[2].time, Some(62.0));
+        assert_eq!(game.start, Board::new());
+        assert_eq!(game.to_string().parse(), Ok(game.clone()));
+
+        let board = game.replay().unwrap();
+        assert_eq!(transcript::emit(&board, false).unwrap(), "f5d6c3");
+        assert!(GgfGame::from_ui_board(&board)
+            .to_string()
+            .contains("B[F5]W[D6]B[C3]"));
+
+        // passes are written, and can be replayed.
+        let board = transcript::parse("e6f6g6g7c4h6h8f8d6").unwrap();
+        let text = GgfGame::from_ui_board(&board).to_string();
+        assert!(text.contains("W[F8]B[PA]W[D6]"));
+        let game: GgfGame = text.parse().unwrap();
+        assert_eq!(game.replay().unwrap().raw_board(), board.raw_board());
+    }
+
+    #[test]
+    fn games_from_other_positions() {
+        // white to move in a position where black has just made a move.
+        let mut start = Board::new();
+        start.put_stone(Position::from_str("f5").unwrap().as_bits());
+        let mut game = GgfGame::from_ui_board(&UiBoard::from_board(start.clone()));
+        game.moves.push(GgfMove {
+            turn: Turn::White,
+            action: Action::Move(Position::from_str("d6").unwrap()),
+            eval: None,
+            time: None,
+        });
+
+        let text = game.to_string();
+        assert!(text.contains("BO[8 -------- -------- -------- ---O*--- ---***-- "));
+        let games = parse_games(&format!("junk\n{}\n{}\n", text, text)).unwrap();
+        assert_eq!(games.len(), 2);
+        let commented = text.replacen("(;", "(;CO[(; wink ;)]", 1);
+        let games = parse_games(&format!("{}\n{}\n", commented, text)).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("CO"), Some("(; wink ;)"));
+        assert!(parse_games(&text.replace(";)", ";")).is_err());
+        assert_eq!(games[0].start, start);
+        assert_eq!(games[0].replay().unwrap().history().len(), 1);
+
+        game.moves[0].turn = Turn::Black;
+        assert!(game.replay().is_err());
+        assert!("(;BO[8 ***];)".parse::<GgfGame>().is_err());
+    }
+
+    #[test]
+    fn updated_games_keep_tags_and_notes() {
+        let text = "(;GM[Othello]PB[alice]PW[bob]TI[05:00]RE[+4.000]\
+                    BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]\
+                    B[F5/1.5/3]W[D6/-1.5/2.25]B[C3//1];)";
+        let game: GgfGame = text.parse().unwrap();
+        let mut board = game.replay().unwrap();
+        board.undo().unwrap();
+        board.put_stone(Position::from_str("c5").unwrap()).unwrap();
+
+        let updated = game.updated(&board);
+        assert_eq!(updated.tag("PB"), Some("alice"));
+        assert_eq!(updated.tag("TI"), Some("05:00"));
+        assert_eq!(updated.tag("RE"), None);
+        assert!(updated
+            .to_string()
+            .contains("B[F5/1.5/3]W[D6/-1.5/2.25]B[C5]"));
+    }
+}
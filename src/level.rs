@@ -0,0 +1,114 @@
+//! Difficulty levels of `CleverRobotPlayer`.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Level {
+    Beginner,
+    Easy,
+    Medium,
+    #[default]
+    Hard,
+    Expert,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [
+        Level::Beginner,
+        Level::Easy,
+        Level::Medium,
+        Level::Hard,
+        Level::Expert,
+    ];
+
+    /// Search depth while many holes remain.
+    pub fn depth(self) -> i32 {
+        match self {
+            Level::Beginner => 1,
+            Level::Easy => 2,
+            Level::Medium => 3,
+            Level::Hard => 5,
+            Level::Expert => 7,
+        }
+    }
+
+    /// Solve the game exactly once this many holes or fewer remain.
+    pub fn endgame_holes(self) -> u32 {
+        match self {
+            Level::Beginner => 0,
+            Level::Easy => 6,
+            Level::Medium => 10,
+            Level::Hard => 14,
+            Level::Expert => 16,
+        }
+    }
+
+    /// Amplitude of random noise added to evaluations, in the unit of
+    /// the evaluator; for `TableEvaluator`, points of its square table
+    /// where a corner is 30.
+    pub fn noise(self) -> i32 {
+        match self {
+            Level::Beginner => 30,
+            Level::Easy => 15,
+            Level::Medium => 6,
+            Level::Hard | Level::Expert => 0,
+        }
+    }
+
+    /// Probability of playing a move other than the best one.
+    pub fn blunder_rate(self) -> f64 {
+        match self {
+            Level::Beginner => 0.25,
+            Level::Easy => 0.1,
+            Level::Medium => 0.03,
+            Level::Hard | Level::Expert => 0.0,
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        Level::ALL
+            .iter()
+            .copied()
+            .find(|level| level.to_string() == name.to_lowercase())
+            .ok_or_else(|| format!("unknown level '{}'", name))
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Level::Beginner => "beginner",
+            Level::Easy => "easy",
+            Level::Medium => "medium",
+            Level::Hard => "hard",
+            Level::Expert => "expert",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn levels_get_stronger() {
+        for pair in Level::ALL.windows(2) {
+            let (weaker, stronger) = (pair[0], pair[1]);
+            assert!(weaker.depth() <= stronger.depth());
+            assert!(weaker.endgame_holes() <= stronger.endgame_holes());
+            assert!(weaker.noise() >= stronger.noise());
+            assert!(weaker.blunder_rate() >= stronger.blunder_rate());
+        }
+        for level in Level::ALL.iter() {
+            assert_eq!(level.to_string().parse(), Ok(*level));
+        }
+        assert_eq!("Expert".parse(), Ok(Level::Expert));
+        assert!("grandmaster".parse::<Level>().is_err());
+    }
+}
@@ -4,16 +4,37 @@
 #[macro_use]
 pub mod utils;
 
+pub mod arena;
 pub mod bitboard;
 pub mod board;
+pub mod book;
 pub mod dumb_screen;
+pub mod endgame;
+pub mod evaluator;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod external;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod factory;
 pub mod game;
+pub mod ggf;
 pub mod history;
+pub mod level;
+pub mod mcts;
 pub mod minimax;
+pub mod obf;
 pub mod player;
 pub mod position;
+pub mod screen;
+pub mod selfplay;
+pub mod trainer;
+pub mod transcript;
+pub mod transposition;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tui_screen;
 pub mod ui_board;
 pub mod wasm_screen;
+pub mod wthor;
+pub mod zobrist;
 
 use wasm_bindgen::prelude::*;
 
@@ -1,70 +1,1065 @@
 //! flipout is a reversi (Othello) implementation in Rust
 //!
 
+use flipout::arena::{self, MatchConfig};
+use flipout::board::{Board, Turn};
+use flipout::book::Book;
 use flipout::dumb_screen::*;
+use flipout::endgame::{self, Solution, SolveMode};
+use flipout::evaluator::{PatternEvaluator, TableEvaluator};
+use flipout::factory;
+use flipout::ggf::{self, GgfGame};
+use flipout::level::Level;
+use flipout::mcts::MctsPlayer;
+use flipout::minimax::{self, SearchReport};
+use flipout::obf::ObfLine;
 use flipout::player::*;
+use flipout::position::Position;
+use flipout::screen::Screen;
+use flipout::selfplay::{self, SelfPlayConfig};
+use flipout::trainer::{self, TrainConfig};
+use flipout::transcript;
+use flipout::tui_screen::{TuiPlayer, TuiScreen};
 use flipout::ui_board::UiBoard;
-use std::io::{self, BufReader};
+use flipout::wthor;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use std::{env, process};
 
-fn usage_and_exit() {
-    eprint!(
-        r#"flipout - a reversi (Othello) implementation in Rust.
-usage: flipout [-a] [-r]
-  -a: Automatic demo mode
+type CliResult<T = ()> = Result<T, Box<dyn Error>>;
+
+/// A subcommand such as `flipout play`.
+struct Command {
+    name: &'static str,
+    summary: &'static str,
+    /// Paragraphs of the help.
+    help: &'static [&'static str],
+    run: fn(Args) -> CliResult,
+}
+
+const COMMANDS: [Command; 9] = [
+    Command {
+        name: "play",
+        summary: "Play a game against the computer (default)",
+        help: &[PLAY_HELP, factory::SPEC_HELP, COMPUTER_OPTIONS],
+        run: play_command,
+    },
+    Command {
+        name: "analyze",
+        summary: "Rank every move of positions",
+        help: &[ANALYZE_HELP, COMPUTER_OPTIONS, POSITION_HELP],
+        run: analyze,
+    },
+    Command {
+        name: "solve",
+        summary: "Solve positions exactly",
+        help: &[SOLVE_HELP, POSITION_HELP],
+        run: solve,
+    },
+    Command {
+        name: "perft",
+        summary: "Count leaf nodes of the game tree",
+        help: &[PERFT_HELP, POSITION_HELP],
+        run: perft,
+    },
+    Command {
+        name: "selfplay",
+        summary: "Record positions of games between computers",
+        help: &[SELFPLAY_HELP],
+        run: selfplay,
+    },
+    Command {
+        name: "train",
+        summary: "Fit pattern evaluation weights to records",
+        help: &[TRAIN_HELP],
+        run: train,
+    },
+    Command {
+        name: "book",
+        summary: "Create or extend an opening book",
+        help: &[BOOK_HELP],
+        run: book,
+    },
+    Command {
+        name: "match",
+        summary: "Play the minimax player against the Monte Carlo player",
+        help: &[MATCH_HELP],
+        run: match_players,
+    },
+    Command {
+        name: "replay",
+        summary: "Show or convert recorded games",
+        help: &[REPLAY_HELP],
+        run: replay,
+    },
+];
+
+const COMPUTER_OPTIONS: &str = r#"  -l LEVEL: Computer level: beginner, easy, medium, hard (default) or expert
+  -t MSEC: Let computer think MSEC milliseconds for each move
+  -e WEIGHTS: Let computer evaluate positions by pattern WEIGHTS file
+  -b BOOK: Let computer play opening moves from BOOK file
+  -j THREADS: Let computer search by THREADS threads (needs 'parallel' feature)
+  -s SEED: Make computer moves reproducible by random SEED
+"#;
+
+const POSITION_HELP: &str = r#"  MOVES: Moves from the initial position such as f5d6c3
+  POSITION: 64 squares of X, O or - from a1 to h8 and the side to move,
+    optionally followed by OBF move scores (such as "...-- X; G8:+18;")
+"#;
+
+const PLAY_HELP: &str = r#"usage: flipout play [-c COLOR] [-a] [-B PLAYER] [-W PLAYER] [-r] [-p] [-i GAME] [-o GAME] [COMPUTER OPTIONS]
+Play a game against the computer on the terminal, in full screen with
+the cursor moved by arrow keys when it is a terminal.
+  -c COLOR: Play black (default) or white
+  -a: Automatic demo mode; a random player plays instead of you
+  -B PLAYER: Let PLAYER play black instead of the default
+  -W PLAYER: Let PLAYER play white instead of the default
+  -r: Reverse color for dark terminals
+  -p: Plain screen and typed moves even on a terminal
+  -i GAME: Continue the game in GAME file (transcript or .ggf)
+  -o GAME: Save the game to GAME file at the end (transcript or .ggf)
+  Type a move such as "f5", or "help" for commands on the plain screen.
+  PLAYER: TYPE[:KEY=VALUE,...] such as "clever:level=easy" of TYPE:
+"#;
+
+const ANALYZE_HELP: &str = r#"usage: flipout analyze [-n TOP] [-f FORMAT] [-i FILE] [COMPUTER OPTIONS] [MOVES | POSITION]
+Rank every move of the position by the computer.
+  -n TOP: Show only the best TOP moves
+  -f FORMAT: Output text (default) or obf
+  -i FILE: Analyze every position in FILE, one MOVES or POSITION per line
+"#;
+
+const SOLVE_HELP: &str = r#"usage: flipout solve [-w] [-t MSEC] [-f FORMAT] (-i FILE | MOVES | POSITION)
+Solve the position exactly, and compare with the expected scores of
+POSITION if any.  Positions of more than 24 empties are refused
+unless -t is given.
+  -w: Tell only win, loss or draw
+  -t MSEC: Give up after MSEC milliseconds for each position instead
+  -f FORMAT: Output text (default) or obf
+  -i FILE: Solve every position in FILE, one MOVES or POSITION per line
+"#;
+
+const PERFT_HELP: &str = r#"usage: flipout perft DEPTH [MOVES | POSITION]
+Count leaf nodes of the game tree up to DEPTH plies.
+"#;
+
+const SELFPLAY_HELP: &str = r#"usage: flipout selfplay [-n GAMES] [-p PLIES] [-d DEPTH] [-s SEED] [-o RECORDS]
+Record positions of games between computers (to stdout).
+  -n GAMES: Number of games (100)
+  -p PLIES: Number of random moves at the beginning (8)
+  -d DEPTH: Search depth (4)
+  -s SEED: Random seed (0)
+  -o RECORDS: Write to RECORDS file
+"#;
+
+const TRAIN_HELP: &str = r#"usage: flipout train [-i EPOCHS] [-l RATE] -o WEIGHTS RECORDS...
+Fit pattern evaluation WEIGHTS to RECORDS files.
+  -i EPOCHS: Number of epochs (20)
+  -l RATE: Learning rate (0.005)
+"#;
+
+const BOOK_HELP: &str = r#"usage: flipout book [-p PLIES] [-d DEPTH] -o BOOK [TRANSCRIPTS...]
+Create or extend opening BOOK file.
+  -p PLIES: Number of plies from the beginning (8)
+  -d DEPTH: Search depth to score moves (8)
+  TRANSCRIPTS: Files of games (such as f5d6c3) in lines, or WTHOR
+    .wtb files; without them, all moves within PLIES are added
+"#;
+
+const MATCH_HELP: &str = r#"usage: flipout match [-n GAMES] [-p PLIES] [-s SEED] [-l LEVEL] [-i ITERATIONS] [-t MSEC]
+Play the minimax player against the Monte Carlo player.
+  -n GAMES: Number of games (10)
+  -p PLIES: Number of random moves at the beginning (4)
+  -s SEED: Random seed (0)
+  -l LEVEL: Level of the minimax player (hard)
+  -i ITERATIONS: Playouts of the Monte Carlo player for each move (10000)
+  -t MSEC: Let both players think MSEC milliseconds for each move
+"#;
+
+const REPLAY_HELP: &str = r#"usage: flipout replay [-f FORMAT] [-n NUMBER] [-r] GAMES
+Replay and check the games in GAMES file: transcripts in lines, GGF
+(.ggf) or WTHOR (.wtb, with WTHOR.JOU and WTHOR.TRN beside it if any).
+  -f FORMAT: Output board (default; after every move), transcript,
+    ggf or obf (final positions)
+  -n NUMBER: Only the NUMBER-th game
   -r: Reverse color for dark terminals
-"#
-    );
-    process::exit(-1);
+"#;
+
+fn usage() -> String {
+    let mut usage = "flipout - a reversi (Othello) implementation in Rust.\n\
+                     usage: flipout [COMMAND] [OPTIONS]\n\
+                     commands:\n"
+        .to_string();
+
+    for command in COMMANDS.iter() {
+        usage += &format!("  {:9} {}\n", command.name, command.summary);
+    }
+    usage + "Run 'flipout help COMMAND' for the options of COMMAND.\n"
+}
+
+/// Arguments of a command.
+struct Args {
+    command: &'static Command,
+    args: std::vec::IntoIter<String>,
 }
 
-fn play(mut board: UiBoard, screen: DumbScreen, mut black: impl Player, mut white: impl Player) {
-    screen.update_screen(&board);
+impl Args {
+    /// Next argument.  Help of the command is shown for "-h" or
+    /// "--help".
+    fn next(&mut self) -> Option<String> {
+        let arg = self.args.next()?;
+
+        if arg == "-h" || arg == "--help" {
+            print!("{}", self.command.help.concat());
+            process::exit(0);
+        }
+        Some(arg)
+    }
+
+    /// Parse the value of `option`, or exit with usage.
+    fn value<T: FromStr>(&mut self, option: &str) -> T {
+        match self.args.next().map(|value| value.parse()) {
+            Some(Ok(value)) => value,
+            Some(Err(_)) => self.usage_error(&format!("invalid value for {}", option)),
+            None => self.usage_error(&format!("{} needs a value", option)),
+        }
+    }
+
+    /// Value of `option` which must be one of `choices`.
+    fn choice(&mut self, option: &str, choices: &[&'static str]) -> &'static str {
+        let value: String = self.value(option);
+
+        match choices.iter().find(|&&choice| choice == value) {
+            Some(choice) => choice,
+            None => self.usage_error(&format!("{} must be one of {}", option, choices.join(", "))),
+        }
+    }
 
-    loop {
-        let action = if board.is_black_turn() {
-            black.action(&board)
+    fn usage_error(&self, message: &str) -> ! {
+        eprintln!("flipout {}: {}", self.command.name, message);
+        eprintln!("Run 'flipout help {}' for usage.", self.command.name);
+        process::exit(2);
+    }
+}
+
+/// Options of `CleverRobotPlayer` shared by commands.
+#[derive(Default)]
+struct ComputerOptions {
+    level: Level,
+    time_budget: Option<Duration>,
+    weights: Option<String>,
+    book: Option<String>,
+    #[cfg(feature = "parallel")]
+    threads: Option<usize>,
+    seed: Option<u64>,
+}
+
+impl ComputerOptions {
+    /// Take `option` and its value from `args`; false if `option` is
+    /// not one of them.
+    fn parse(&mut self, option: &str, args: &mut Args) -> bool {
+        match option {
+            "-l" => self.level = args.value(option),
+            "-t" => self.time_budget = Some(Duration::from_millis(args.value(option))),
+            "-e" => self.weights = Some(args.value(option)),
+            "-b" => self.book = Some(args.value(option)),
+            #[cfg(feature = "parallel")]
+            "-j" => self.threads = Some(args.value(option)),
+            "-s" | "--seed" => self.seed = Some(args.value(option)),
+            _ => return false,
+        }
+        true
+    }
+
+    fn build(&self) -> CliResult<CleverRobotPlayer> {
+        let mut player = CleverRobotPlayer::new().with_level(self.level);
+
+        if let Some(budget) = self.time_budget {
+            player = player.with_time_budget(budget);
+        }
+        if let Some(path) = &self.weights {
+            let evaluator = PatternEvaluator::load(path)
+                .map_err(|err| format!("can't load {}: {}", path, err))?;
+            player = player.with_evaluator(Box::new(evaluator));
+        }
+        if let Some(path) = &self.book {
+            let book = Book::load(path).map_err(|err| format!("can't load {}: {}", path, err))?;
+            player = player.with_book(book);
+        }
+        #[cfg(feature = "parallel")]
+        if let Some(threads) = self.threads {
+            player = player.with_threads(threads);
+        }
+        if let Some(seed) = self.seed {
+            player = player.with_seed(seed);
+        }
+        Ok(player)
+    }
+}
+
+////////////////////////////////////////////////////////////////
+// Games and positions
+
+/// A game on the terminal, controlled by the commands of players.
+struct TerminalGame<'a> {
+    board: &'a mut UiBoard,
+    screen: &'a dyn Screen,
+    /// Engine for hints of players.
+    adviser: &'a CleverRobotPlayer,
+    /// Record of the loaded game, whose tags and notes of moves are
+    /// written back to saved games.
+    record: GgfGame,
+    /// Names of black and white for saved games.
+    names: [&'a str; 2],
+    quit: bool,
+}
+
+impl Controller for TerminalGame<'_> {
+    fn board(&self) -> &UiBoard {
+        self.board
+    }
+
+    fn show_board(&self) {
+        self.screen.update_screen(self.board);
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        save_game(path, self.board, &self.record, self.names)
+            .map_err(|err| format!("can't save {}: {}", path, err))
+    }
+
+    fn load(&mut self, path: &str) -> Result<(), String> {
+        let (board, record) =
+            load_game(path).map_err(|err| format!("can't load {}: {}", path, err))?;
+        *self.board = board;
+        self.record = record;
+        self.screen.update_screen(self.board);
+        Ok(())
+    }
+
+    fn analyze(&self, top: Option<usize>) -> Vec<SearchReport> {
+        self.adviser.analyze(self.board.raw_board(), top)
+    }
+
+    fn quit(&mut self) {
+        self.quit = true;
+    }
+}
+
+fn play(game: &mut TerminalGame, black: &mut dyn Player, white: &mut dyn Player) {
+    game.screen.update_screen(game.board);
+
+    while !game.board.is_game_over() && !game.quit {
+        let player: &mut dyn Player = if game.board.is_black_turn() {
+            &mut *black
         } else {
-            white.action(&board)
+            &mut *white
+        };
+        let turn = game.board.raw_board().turn;
+        let action = match player.interact(game) {
+            Some(action) => action,
+            None => continue,
         };
+        let board = &mut *game.board;
 
-        match action {
+        let error = match action {
             Action::GiveUp => break,
-            Action::Pass => board.pass().is_ok(),
-            Action::Move(pos) => board.put_stone(pos).is_ok(),
+            Action::Pass => board.pass().err().map(String::from),
+            Action::Move(pos) => board.put_stone(pos).err().map(String::from),
+            Action::Undo => board.undo_until(turn).err().map(String::from),
+            Action::Redo => board.redo_until(turn).err().map(String::from),
         };
 
-        screen.update_screen(&board);
+        game.screen.update_screen(game.board);
 
-        if board.is_game_over() {
-            break;
+        if let Some(error) = error {
+            game.screen.message(&error);
+        } else if let Some(report) = player.last_report() {
+            game.screen.message(&report.to_string());
         }
     }
+    if let Some(transcript) = transcript::emit(game.board, false) {
+        game.screen.message(&format!("Transcript: {}", transcript));
+    }
 }
 
-fn main() {
-    let mut opt_auto_demo = false;
-    let mut opt_reverse_video = false;
+fn is_ggf(path: &str) -> bool {
+    path.to_lowercase().ends_with(".ggf")
+}
+
+/// Games in `path`: transcripts in lines, GGF (.ggf) or WTHOR (.wtb).
+/// Broken games of transcripts or WTHOR are reported and skipped.
+fn load_games(path: &str) -> CliResult<Vec<GgfGame>> {
+    let mut games = Vec::new();
+
+    if path.to_lowercase().ends_with(".wtb") {
+        let mut db = wthor::Database::load(path)?;
+        let dir = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+
+        for name in &["WTHOR.JOU", "wthor.jou"] {
+            if dir.join(name).exists() {
+                db.load_players(dir.join(name))?;
+            }
+        }
+        for name in &["WTHOR.TRN", "wthor.trn"] {
+            if dir.join(name).exists() {
+                db.load_tournaments(dir.join(name))?;
+            }
+        }
+        for game in db.games() {
+            match game {
+                Ok(game) => {
+                    let board = transcript::parse(&game.transcript())?;
+                    let mut record = GgfGame::from_ui_board(&board);
+                    record.set_tag("PC", &game.tournament);
+                    record.set_tag("DT", &game.year.to_string());
+                    record.set_tag("PB", &game.black);
+                    record.set_tag("PW", &game.white);
+                    games.push(record);
+                }
+                Err(reason) => eprintln!("{}: {}", path, reason),
+            }
+        }
+    } else if is_ggf(path) {
+        games = ggf::parse_games(&fs::read_to_string(path)?)?;
+    } else {
+        for (lineno, line) in fs::read_to_string(path)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match transcript::parse(line) {
+                Ok(board) => games.push(GgfGame::from_ui_board(&board)),
+                Err(reason) => eprintln!("{}:{}: {}", path, lineno + 1, reason),
+            }
+        }
+    }
+    Ok(games)
+}
+
+/// The first game in `path`, as `load_games`, with its record.
+fn load_game(path: &str) -> CliResult<(UiBoard, GgfGame)> {
+    match load_games(path)?.into_iter().next() {
+        Some(game) => Ok((game.replay()?, game)),
+        None => Err(format!("no game in {}", path).into()),
+    }
+}
+
+/// Save the game on `board` to `path`, as GGF if it ends with ".ggf",
+/// otherwise as a transcript.  GGF keeps the tags and notes of moves
+/// of `record`, the game `board` was loaded from.
+fn save_game(path: &str, board: &UiBoard, record: &GgfGame, names: [&str; 2]) -> CliResult {
+    let text = if is_ggf(path) {
+        let mut game = record.updated(board);
+        game.set_tag("PB", names[0]);
+        game.set_tag("PW", names[1]);
+        game.to_string()
+    } else {
+        transcript::emit(board, false).ok_or("a game not from the initial position needs .ggf")?
+    };
+    fs::write(path, text + "\n")?;
+    Ok(())
+}
+
+/// Position of `arg`, which is either moves such as "f5d6c3" from the
+/// initial position or an OBF line.
+fn parse_position(arg: &str) -> Result<ObfLine, String> {
+    // moves always have digits, and positions never do before ';'.
+    let board_part = arg.split(';').next().unwrap_or("");
+
+    if board_part.chars().any(|c| c.is_ascii_digit()) || arg.is_empty() {
+        transcript::parse(arg)
+            .map(|game| ObfLine {
+                board: game.raw_board().clone(),
+                scores: Vec::new(),
+            })
+            .map_err(|err| err.to_string())
+    } else {
+        arg.parse()
+    }
+}
 
-    let tty = Box::new(BufReader::new(io::stdin()));
+/// Positions from lines of `input` if any, otherwise from `arg`.
+fn read_positions(input: Option<String>, arg: &str) -> CliResult<Vec<ObfLine>> {
+    let text = match input {
+        Some(path) => fs::read_to_string(path)?,
+        None => return Ok(vec![parse_position(arg)?]),
+    };
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(lineno, line)| {
+            parse_position(line.trim())
+                .map_err(|err| format!("line {}: {}", lineno + 1, err).into())
+        })
+        .collect()
+}
 
-    for arg in env::args().skip(1) {
+fn move_name(mov: u64) -> String {
+    match mov {
+        0 => "pass".to_string(),
+        _ => Position::new(mov).to_string(),
+    }
+}
+
+////////////////////////////////////////////////////////////////
+// Commands
+
+fn play_command(mut args: Args) -> CliResult {
+    let mut human_color = Turn::Black;
+    let mut auto_demo = false;
+    let mut reverse_video = false;
+    let mut plain = false;
+    let mut input = None;
+    let mut output = None;
+    let mut specs: [Option<String>; 2] = [None, None];
+    let mut computer = ComputerOptions::default();
+
+    while let Some(arg) = args.next() {
         match &*arg {
-            "-a" => opt_auto_demo = true,
-            "-r" => opt_reverse_video = true,
-            _ => {
-                usage_and_exit();
+            "-c" => {
+                human_color = match args.choice("-c", &["black", "white"]) {
+                    "black" => Turn::Black,
+                    _ => Turn::White,
+                }
+            }
+            "-a" => auto_demo = true,
+            "-r" => reverse_video = true,
+            "-p" => plain = true,
+            "-i" => input = Some(args.value::<String>("-i")),
+            "-o" => output = Some(args.value::<String>("-o")),
+            "-B" => specs[0] = Some(args.value("-B")),
+            "-W" => specs[1] = Some(args.value("-W")),
+            option if computer.parse(option, &mut args) => (),
+            _ => args.usage_error(&format!("unknown option '{}'", arg)),
+        }
+    }
+
+    let (mut board, record) = match input {
+        Some(path) => load_game(&path)?,
+        None => {
+            let board = UiBoard::new();
+            let record = GgfGame::from_ui_board(&board);
+            (board, record)
+        }
+    };
+
+    // sides without a spec are you (or a random player in demo) and
+    // the computer of COMPUTER OPTIONS.
+    let human_index = if human_color.is_black() { 0 } else { 1 };
+    let humans: Vec<bool> = specs
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| match spec {
+            Some(spec) => spec.trim() == "human",
+            None => index == human_index && !auto_demo,
+        })
+        .collect();
+
+    // the full-screen UI is for you on a terminal.
+    let tui = if !plain
+        && humans.contains(&true)
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+    {
+        match TuiScreen::new() {
+            Ok(tui) => Some(Rc::new(tui)),
+            Err(err) => {
+                eprintln!("flipout play: plain screen for the terminal: {}", err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let mut names = Vec::new();
+    let mut players = Vec::new();
+
+    for (index, spec) in specs.iter().enumerate() {
+        let (name, player): (String, Box<dyn Player>) = match spec {
+            _ if humans[index] => match &tui {
+                Some(tui) => ("human".to_string(), Box::new(TuiPlayer::new(tui.clone()))),
+                None => ("human".to_string(), factory::player_from_spec("human")?),
+            },
+            Some(spec) => (spec.clone(), factory::player_from_spec(spec)?),
+            None if index != human_index => (
+                format!("flipout ({})", computer.level),
+                Box::new(computer.build()?),
+            ),
+            None => {
+                // a different stream from the computer.
+                let mut robot = RobotPlayer::new();
+                if let Some(seed) = computer.seed {
+                    robot = robot.with_seed(seed.wrapping_add(1));
+                }
+                ("random".to_string(), Box::new(robot))
+            }
+        };
+        names.push(name);
+        players.push(player);
+    }
+
+    // keep the record of moves on the screen unless you are playing.
+    let dumb_screen = DumbScreen::new(!humans.contains(&true), reverse_video);
+    let screen: &dyn Screen = match &tui {
+        Some(tui) => tui.as_ref(),
+        None => &dumb_screen,
+    };
+    let adviser = computer.build()?;
+    let mut game = TerminalGame {
+        board: &mut board,
+        screen,
+        adviser: &adviser,
+        record,
+        names: [&names[0], &names[1]],
+        quit: false,
+    };
+    let (black, white) = players.split_at_mut(1);
+    play(&mut game, black[0].as_mut(), white[0].as_mut());
+
+    if let Some(path) = output {
+        save_game(&path, game.board, &game.record, game.names)?;
+    }
+    Ok(())
+}
+
+fn solve(mut args: Args) -> CliResult {
+    let mut mode = SolveMode::Exact;
+    let mut time_budget = None;
+    let mut format = "text";
+    let mut input = None;
+    let mut position = String::new();
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-w" => mode = SolveMode::WinLossDraw,
+            "-t" => time_budget = Some(Duration::from_millis(args.value("-t"))),
+            "-f" => format = args.choice("-f", &["text", "obf"]),
+            "-i" => input = Some(args.value("-i")),
+            _ if position.is_empty() => position = arg,
+            _ => args.usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+    // the initial position would take forever.
+    if input.is_none() && position.is_empty() {
+        args.usage_error("missing MOVES or POSITION");
+    }
+
+    let mut expected = 0;
+    let mut matched = 0;
+
+    for position in read_positions(input, &position)? {
+        let board = &position.board;
+        let solution = match time_budget {
+            Some(budget) => solve_in_time(board, budget)?,
+            None => {
+                endgame::check_holes(board)?;
+                endgame::solve(board, mode)
             }
+        };
+        let best = solution.best_move.unwrap_or(0);
+
+        if !position.scores.is_empty() {
+            expected += 1;
+            if position.best_moves().contains(&best) {
+                matched += 1;
+            }
+        }
+
+        if format == "obf" {
+            let line = ObfLine {
+                board: board.clone(),
+                scores: vec![(best, solution.score)],
+            };
+            println!("{}", line);
+            continue;
+        }
+
+        print!("{}", board);
+        println!("Position: {}", board.to_notation());
+        println!("Turn: {}", board.turn);
+        println!("Best move: {}", move_name(best));
+        if let Some(&(_, score)) = position.scores.iter().max_by_key(|(_, score)| *score) {
+            let best: Vec<String> = position.best_moves().into_iter().map(move_name).collect();
+            println!("Expected: {} ({:+})", best.join(" "), score);
+        }
+        match mode {
+            SolveMode::Exact => println!("Result: {} ({:+})", solution.outcome(), solution.score),
+            SolveMode::WinLossDraw => println!("Result: {}", solution.outcome()),
         }
+        println!("Nodes: {}", solution.nodes);
     }
 
-    let board = UiBoard::new();
-    let screen = DumbScreen::new(opt_auto_demo, opt_reverse_video);
-    let white = CleverRobotPlayer::new();
+    if expected > 1 {
+        eprintln!("best moves as expected: {}/{}", matched, expected);
+    }
+    Ok(())
+}
+
+/// Solve `board` by iterative deepening to the end of the game, or
+/// fail if `budget` runs out before the deepest iteration.
+fn solve_in_time(board: &Board, budget: Duration) -> CliResult<Solution> {
+    // the search needs a move to start from.
+    if board.legal_moves() == 0 {
+        endgame::check_holes(board)?;
+        return Ok(endgame::solve(board, SolveMode::Exact));
+    }
 
-    if opt_auto_demo {
-        play(board, screen, RobotPlayer::new(), white);
+    // passes take plies too, but never two in a row.
+    let max_depth = 2 * board.count_hole() as i32;
+    let report = minimax::iterative_deepening(board, &TableEvaluator, max_depth, Some(budget))
+        .ok_or("no move to search")?;
+
+    if !report.exact {
+        return Err(format!(
+            "not solved in {} ms; searched {} plies of {} empties",
+            budget.as_millis(),
+            report.depth,
+            board.count_hole()
+        )
+        .into());
+    }
+    Ok(Solution {
+        best_move: Some(report.mov),
+        score: report.score / TableEvaluator::SCALE,
+        pv: report.pv.iter().map(Position::as_bits).collect(),
+        nodes: report.nodes,
+    })
+}
+
+fn analyze(mut args: Args) -> CliResult {
+    let mut top = None;
+    let mut format = "text";
+    let mut input = None;
+    let mut computer = ComputerOptions::default();
+    let mut position = String::new();
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-n" => top = Some(args.value("-n")),
+            "-f" => format = args.choice("-f", &["text", "obf"]),
+            "-i" => input = Some(args.value("-i")),
+            option if computer.parse(option, &mut args) => (),
+            _ if position.is_empty() => position = arg,
+            _ => args.usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    let player = computer.build()?;
+
+    for position in read_positions(input, &position)? {
+        let board = position.board;
+        let reports = player.analyze(&board, top);
+
+        if format == "obf" {
+            let scores = reports.iter().map(|r| (r.mov, r.score)).collect();
+            println!("{}", ObfLine { board, scores });
+            continue;
+        }
+
+        print!("{}", board);
+        println!("Position: {}", board.to_notation());
+        println!("Turn: {}", board.turn);
+        println!("rank move   score  pv");
+
+        for (rank, report) in reports.iter().enumerate() {
+            let pv: Vec<String> = report.pv.iter().map(|pos| pos.to_string()).collect();
+            println!(
+                "{:4} {:4} {:+7}{} {}",
+                rank + 1,
+                Position::new(report.mov),
+                report.score,
+                if report.exact { "*" } else { " " },
+                pv.join(" ")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn perft(mut args: Args) -> CliResult {
+    // not `Args::value`, so that "-h" shows help.
+    let depth: u32 = match args.next() {
+        Some(depth) => depth
+            .parse()
+            .unwrap_or_else(|_| args.usage_error("invalid value for DEPTH")),
+        None => args.usage_error("DEPTH is required"),
+    };
+    let position = args.next().unwrap_or_default();
+    let board = parse_position(&position)?.board;
+
+    if let Some(arg) = args.next() {
+        args.usage_error(&format!("unexpected argument '{}'", arg));
+    }
+
+    for d in 1..=depth {
+        let start = Instant::now();
+        let count = board.perft(d);
+        println!(
+            "perft {:2}: {:12} ({:.3}s)",
+            d,
+            count,
+            start.elapsed().as_secs_f64()
+        );
+    }
+    Ok(())
+}
+
+fn selfplay(mut args: Args) -> CliResult {
+    let mut config = SelfPlayConfig::default();
+    let mut output: Box<dyn Write> = Box::new(io::stdout());
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-n" => config.games = args.value("-n"),
+            "-p" => config.random_plies = args.value("-p"),
+            "-d" => config.depth = args.value("-d"),
+            "-s" => config.seed = args.value("-s"),
+            "-o" => {
+                let path: String = args.value("-o");
+                output = Box::new(BufWriter::new(File::create(path)?));
+            }
+            _ => args.usage_error(&format!("unknown option '{}'", arg)),
+        }
+    }
+
+    let mut result = Ok(());
+    let mut games = 0;
+
+    selfplay::generate(&config, &TableEvaluator, |records| {
+        if result.is_ok() {
+            result = selfplay::write_records(&mut output, records);
+        }
+        games += 1;
+        eprint!("\rgames: {}/{}", games, config.games);
+    });
+    eprintln!();
+    result?;
+    output.flush()?;
+    Ok(())
+}
+
+fn train(mut args: Args) -> CliResult {
+    let mut config = TrainConfig::default();
+    let mut weights_path = None;
+    let mut records = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-i" => config.epochs = args.value("-i"),
+            "-l" => config.learning_rate = args.value("-l"),
+            "-o" => weights_path = Some(args.value::<String>("-o")),
+            _ if arg.starts_with('-') => args.usage_error(&format!("unknown option '{}'", arg)),
+            path => {
+                let file = BufReader::new(File::open(path)?);
+                records.extend(selfplay::read_records(file)?);
+            }
+        }
+    }
+
+    let weights_path = weights_path.unwrap_or_else(|| args.usage_error("-o is required"));
+    eprintln!("records: {}", records.len());
+
+    let evaluator = trainer::train(&records, &config, |epoch, rmse| {
+        eprintln!("epoch {:3}: rmse {:.3} discs", epoch, rmse);
+    });
+    evaluator.save(weights_path)?;
+    Ok(())
+}
+
+fn book(mut args: Args) -> CliResult {
+    let mut plies = 8;
+    let mut depth = 8;
+    let mut book_path = None;
+    let mut transcripts = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-p" => plies = args.value("-p"),
+            "-d" => depth = args.value("-d"),
+            "-o" => book_path = Some(args.value::<String>("-o")),
+            _ if arg.starts_with('-') => args.usage_error(&format!("unknown option '{}'", arg)),
+            path => transcripts.push(path.to_string()),
+        }
+    }
+
+    let book_path = book_path.unwrap_or_else(|| args.usage_error("-o is required"));
+    let mut book = if Path::new(&book_path).exists() {
+        Book::load(&book_path)?
     } else {
-        play(board, screen, HumanPlayer::new(tty), white);
+        Book::new()
+    };
+
+    if transcripts.is_empty() {
+        book.expand(plies, depth);
+    }
+    for path in transcripts {
+        if path.to_lowercase().ends_with(".wtb") {
+            for game in wthor::Database::load(&path)?.games() {
+                let added = game.and_then(|game| {
+                    book.add_transcript(&game.transcript(), plies, depth)
+                        .map_err(|err| err.to_string())
+                });
+                if let Err(reason) = added {
+                    eprintln!("{}: {}", path, reason);
+                }
+            }
+            continue;
+        }
+        let file = BufReader::new(File::open(&path)?);
+
+        for (lineno, line) in file.lines().enumerate() {
+            if let Err(reason) = book.add_transcript(line?.trim(), plies, depth) {
+                eprintln!("{}:{}: {}", path, lineno + 1, reason);
+            }
+        }
+    }
+
+    eprintln!("positions: {}", book.len());
+    book.save(book_path)?;
+    Ok(())
+}
+
+fn match_players(mut args: Args) -> CliResult {
+    let mut config = MatchConfig::default();
+    let mut level = Level::default();
+    let mut iterations = None;
+    let mut budget = None;
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-n" => config.games = args.value("-n"),
+            "-p" => config.random_plies = args.value("-p"),
+            "-s" => config.seed = args.value("-s"),
+            "-l" => level = args.value("-l"),
+            "-i" => iterations = Some(args.value("-i")),
+            "-t" => budget = Some(Duration::from_millis(args.value("-t"))),
+            _ => args.usage_error(&format!("unknown option '{}'", arg)),
+        }
+    }
+
+    let mut clever = CleverRobotPlayer::new()
+        .with_level(level)
+        .with_seed(config.seed);
+    let mut mcts = MctsPlayer::new().with_seed(config.seed);
+
+    if let Some(budget) = budget {
+        clever = clever.with_time_budget(budget);
+        mcts = mcts.with_time_budget(budget);
+    }
+    if let Some(iterations) = iterations {
+        mcts = mcts.with_iterations(iterations);
+    }
+
+    let players: [&mut dyn Player; 2] = [&mut clever, &mut mcts];
+    let result = arena::play_match(players, ["minimax", "mcts"], &config, |game, _| {
+        eprint!("\rgames: {}/{}", game, config.games);
+    });
+    eprintln!();
+    print!("{}", result);
+    Ok(())
+}
+
+fn replay(mut args: Args) -> CliResult {
+    let mut format = "board";
+    let mut number = None;
+    let mut reverse_video = false;
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-f" => format = args.choice("-f", &["board", "transcript", "ggf", "obf"]),
+            "-n" => number = Some(args.value::<usize>("-n")),
+            "-r" => reverse_video = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => args.usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    let path = path.unwrap_or_else(|| args.usage_error("GAMES is required"));
+    let screen = DumbScreen::new(true, reverse_video);
+
+    for (game, index) in load_games(&path)?.iter().zip(1..) {
+        if number.is_some_and(|number| number != index) {
+            continue;
+        }
+        let board = match game.replay() {
+            Ok(board) => board,
+            Err(reason) => {
+                eprintln!("{}: game {}: {}", path, index, reason);
+                continue;
+            }
+        };
+
+        match format {
+            "transcript" => match transcript::emit(&board, false) {
+                Some(transcript) => println!("{}", transcript),
+                None => eprintln!("{}: game {}: not from the initial position", path, index),
+            },
+            "ggf" => println!("{}", game),
+            "obf" => println!("{}", board.raw_board().to_notation()),
+            _ => {
+                let names = (game.tag("PB"), game.tag("PW"));
+                println!(
+                    "Game {}: {} vs {}",
+                    index,
+                    names.0.unwrap_or("?"),
+                    names.1.unwrap_or("?")
+                );
+
+                let mut step = UiBoard::from_board(board.start_board().clone());
+                screen.update_screen(&step);
+                for history in board.history() {
+                    if let Action::Move(pos) = history.action {
+                        step.put_stone(pos)?;
+                        println!("\n{} {}", history.turn, pos);
+                        screen.update_screen(&step);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let name = args.next().unwrap_or_else(|| "play".to_string());
+
+    if name == "help" || name == "-h" || name == "--help" {
+        match args.next() {
+            Some(name) => match COMMANDS.iter().find(|command| command.name == name) {
+                Some(command) => print!("{}", command.help.concat()),
+                None => {
+                    eprintln!("flipout: unknown command '{}'", name);
+                    process::exit(2);
+                }
+            },
+            None => print!("{}", usage()),
+        }
+        return;
+    }
+
+    let command = match COMMANDS.iter().find(|command| command.name == name) {
+        Some(command) => command,
+        None => {
+            eprintln!("flipout: unknown command '{}'", name);
+            eprint!("{}", usage());
+            process::exit(2);
+        }
+    };
+    let args = Args {
+        command,
+        args: args.collect::<Vec<_>>().into_iter(),
+    };
+
+    if let Err(err) = (command.run)(args) {
+        eprintln!("flipout {}: {}", command.name, err);
+        process::exit(1);
     }
 }
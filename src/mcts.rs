@@ -0,0 +1,326 @@
+//! Monte Carlo Tree Search player.
+//!
+//! Instead of a heuristic evaluation, moves are judged by the results
+//! of many random games (playouts).  The tree grows toward promising
+//! moves by UCT (Upper Confidence bounds applied to Trees), which
+//! balances moves with high win rates against moves tried only a few
+//! times.
+//!
+
+use crate::bitboard::{Move, MoveIter};
+use crate::board::{Board, Turn};
+use crate::minimax::SearchReport;
+use crate::player::{Action, Player};
+use crate::position::Position;
+use crate::ui_board::UiBoard;
+use crate::utils;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+
+/// Corners, preferred by light playouts.
+const CORNERS: u64 = 0x8100_0000_0000_0081;
+
+/// How playouts choose moves.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Playout {
+    /// Any legal move at random.
+    Random,
+    /// A corner if available, otherwise at random.
+    Light,
+}
+
+/// A node of the search tree, kept in an arena and linked by indices.
+struct Node {
+    board: Board,
+    /// Move which led here from the parent; 0 for pass.
+    mov: Move,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Children not added to the tree yet.
+    unexpanded: Vec<Board>,
+    visits: u32,
+    /// Sum of results for the player who moved into this node:
+    /// 1 for a win, 0.5 for a draw.
+    wins: f64,
+}
+
+impl Node {
+    fn new(board: Board, mov: Move, parent: Option<usize>) -> Self {
+        Node {
+            unexpanded: board.children(),
+            board,
+            mov,
+            parent,
+            children: Vec::new(),
+            visits: 0,
+            wins: 0.0,
+        }
+    }
+}
+
+pub struct MctsPlayer {
+    iterations: Option<u32>,
+    time_budget: Option<Duration>,
+    exploration: f64,
+    playout: Playout,
+    rng: StdRng,
+    last_report: Option<SearchReport>,
+}
+
+impl Default for MctsPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MctsPlayer {
+    /// Iterations for each move unless changed.
+    const DEFAULT_ITERATIONS: u32 = 10_000;
+
+    pub fn new() -> Self {
+        MctsPlayer {
+            iterations: Some(Self::DEFAULT_ITERATIONS),
+            time_budget: None,
+            exploration: std::f64::consts::SQRT_2,
+            playout: Playout::Random,
+            rng: StdRng::from_entropy(),
+            last_report: None,
+        }
+    }
+
+    /// Stop after `iterations` playouts for each move.
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// Stop after `budget` for each move.  The iteration limit is
+    /// removed; set it again by `with_iterations` to stop at whichever
+    /// comes first.
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.iterations = None;
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// UCT exploration constant; larger values try more moves.
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    pub fn with_playout(mut self, playout: Playout) -> Self {
+        self.playout = playout;
+        self
+    }
+
+    /// Make the search reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Search `board` and report the most visited move.
+    ///
+    /// `score` of the report is the win rate of the move in percent,
+    /// and `nodes` is the number of iterations.
+    pub fn search(&mut self, board: &Board) -> Option<SearchReport> {
+        let start = utils::now_ms();
+        let deadline = self.time_budget.map(utils::deadline_after);
+        let mut tree = vec![Node::new(board.clone(), 0, None)];
+        let mut iterations = 0;
+
+        if tree[0].unexpanded.is_empty() {
+            return None;
+        }
+
+        loop {
+            if self.iterations.is_some_and(|limit| iterations >= limit) {
+                break;
+            }
+            if deadline.is_some_and(|deadline| utils::now_ms() >= deadline) && iterations > 0 {
+                break;
+            }
+            self.iterate(&mut tree);
+            iterations += 1;
+        }
+
+        let best = Self::most_visited(&tree, 0)?;
+        let mut pv = Vec::new();
+        let mut node = Some(best);
+
+        while let Some(index) = node {
+            if tree[index].mov != 0 {
+                pv.push(Position::new(tree[index].mov));
+            }
+            node = Self::most_visited(&tree, index);
+        }
+
+        Some(SearchReport {
+            mov: tree[best].mov,
+            score: (tree[best].wins * 100.0 / tree[best].visits as f64).round() as i32,
+            depth: pv.len() as i32,
+            pv,
+            nodes: iterations as u64,
+            elapsed: utils::elapsed_since(start),
+            exact: false,
+        })
+    }
+
+    /// Select, expand, play out and back up once.
+    fn iterate(&mut self, tree: &mut Vec<Node>) {
+        let mut index = 0;
+
+        // selection
+        while tree[index].unexpanded.is_empty() && !tree[index].children.is_empty() {
+            index = self.select(tree, index);
+        }
+
+        // expansion
+        if !tree[index].unexpanded.is_empty() {
+            let unexpanded = &mut tree[index].unexpanded;
+            let child = unexpanded.swap_remove(self.rng.gen_range(0, unexpanded.len()));
+            let parent = &tree[index].board;
+            let mov = (child.black | child.white) & !(parent.black | parent.white);
+
+            tree.push(Node::new(child, mov, Some(index)));
+            let child_index = tree.len() - 1;
+            tree[index].children.push(child_index);
+            index = child_index;
+        }
+
+        // playout
+        let score = self.play_out(tree[index].board.clone());
+
+        // backup
+        let mut node = index;
+        tree[node].visits += 1;
+
+        while let Some(parent) = tree[node].parent {
+            let mover = tree[parent].board.turn;
+            tree[node].wins += Self::reward(score, mover);
+            tree[parent].visits += 1;
+            node = parent;
+        }
+    }
+
+    /// Child of `index` with the highest UCT value.
+    fn select(&self, tree: &[Node], index: usize) -> usize {
+        let log_visits = (tree[index].visits as f64).ln();
+        let uct = |child: usize| {
+            let node = &tree[child];
+            let visits = node.visits as f64;
+            node.wins / visits + self.exploration * (log_visits / visits).sqrt()
+        };
+
+        *tree[index]
+            .children
+            .iter()
+            .max_by(|&&a, &&b| uct(a).partial_cmp(&uct(b)).unwrap())
+            .unwrap()
+    }
+
+    fn most_visited(tree: &[Node], index: usize) -> Option<usize> {
+        tree[index]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&child| tree[child].visits)
+    }
+
+    /// Play `board` to the end, and return the final disc differential
+    /// seen from black.
+    fn play_out(&mut self, mut board: Board) -> i32 {
+        while !board.is_game_over() {
+            let moves = board.legal_moves();
+
+            if moves == 0 {
+                board.pass();
+                continue;
+            }
+            let candidates = match self.playout {
+                Playout::Light if moves & CORNERS != 0 => moves & CORNERS,
+                _ => moves,
+            };
+            let n = self.rng.gen_range(0, candidates.count_ones() as usize);
+            let mov = MoveIter::new(candidates).nth(n).unwrap();
+
+            board.put_stone(mov);
+        }
+        board.final_score(Turn::Black)
+    }
+
+    /// Result of a game ending with `score` (seen from black) for `turn`.
+    fn reward(score: i32, turn: Turn) -> f64 {
+        let score = if turn.is_black() { score } else { -score };
+
+        match score {
+            s if s > 0 => 1.0,
+            s if s < 0 => 0.0,
+            _ => 0.5,
+        }
+    }
+}
+
+impl Player for MctsPlayer {
+    fn action(&mut self, board: &UiBoard) -> Action {
+        self.last_report = self.search(board.raw_board());
+
+        match &self.last_report {
+            Some(report) if report.mov != 0 => Action::Move(Position::new(report.mov)),
+            _ => Action::Pass,
+        }
+    }
+
+    fn last_report(&self) -> Option<&SearchReport> {
+        self.last_report.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::midgame_board;
+    use crate::endgame::{self, SolveMode};
+
+    #[test]
+    fn seeded_search_is_reproducible() {
+        let board = Board::new();
+        let search = |seed| {
+            MctsPlayer::new()
+                .with_iterations(300)
+                .with_seed(seed)
+                .search(&board)
+                .unwrap()
+        };
+        let report = search(1);
+
+        assert!(board.is_legal_move(report.mov));
+        assert_eq!(report.nodes, 300);
+        assert_eq!(search(1).pv, report.pv);
+    }
+
+    #[test]
+    fn finds_winning_moves_near_the_end() {
+        for seed in 0..4_u64 {
+            let board = midgame_board(seed, 6);
+            if board.legal_moves() == 0 {
+                continue;
+            }
+
+            let solution = endgame::solve(&board, SolveMode::Exact);
+            let report = MctsPlayer::new()
+                .with_iterations(2000)
+                .with_seed(seed)
+                .search(&board)
+                .unwrap();
+            let mut child = board.clone();
+            child.put_stone(report.mov);
+
+            if solution.score > 0 {
+                assert!(endgame::solve(&child, SolveMode::Exact).score < 0);
+            }
+        }
+    }
+}
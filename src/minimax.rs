@@ -1,42 +1,724 @@
 //! simple implementation of Mini-Max method.
+//!
+//! The search is written in nega-max form: every score is seen from
+//! the player to move at that node.
 
+use crate::bitboard::{Move, MoveIter, Symmetry};
 use crate::board::{Board, Turn};
-use std::cmp::{max, min};
+use crate::evaluator::{Evaluator, TableEvaluator};
+use crate::position::Position;
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use crate::transposition::SharedTable;
+use crate::transposition::{Bound, Table, TranspositionTable};
+use crate::utils;
+use crate::zobrist;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::cmp::max;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Larger than any score returned by evaluation.
+pub const INFINITY: i32 = 1_000_000;
+
+/// Check the clock once every this many nodes (must be a power of 2).
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Result of `iterative_deepening`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchReport {
+    /// Best move.
+    pub mov: Move,
+    /// Score seen from the player to move.
+    pub score: i32,
+    /// Principal variation: the best move and the expected replies.
+    /// Passes are omitted.
+    pub pv: Vec<Position>,
+    /// Number of searched nodes of all iterations.
+    pub nodes: u64,
+    /// Depth of the deepest completed iteration.
+    pub depth: i32,
+    pub elapsed: Duration,
+    /// Whether `score` is the exact final disc differential, rather
+    /// than a heuristic score.
+    pub exact: bool,
+}
+
+impl fmt::Display for SearchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pv: Vec<String> = self.pv.iter().map(|pos| pos.to_string()).collect();
+
+        write!(
+            f,
+            "depth {} score {:+}{} nodes {} time {:.3}s pv {}",
+            self.depth,
+            self.score,
+            if self.exact { " (exact)" } else { "" },
+            self.nodes,
+            self.elapsed.as_secs_f64(),
+            pv.join(" ")
+        )
+    }
+}
 
 /// return score for board
 pub fn minimax(board: &Board, me: Turn, depth: i32) -> i32 {
-    alpha_beta(board, me, depth, std::i32::MIN, std::i32::MAX)
+    minimax_with_table(board, me, depth, &mut TranspositionTable::default())
 }
 
-fn alpha_beta(board: &Board, me: Turn, depth: i32, mut alpha: i32, mut beta: i32) -> i32 {
-    if depth <= 0 {
-        return board.eval_score(me);
+/// `minimax` with `tt`, which can be kept for a series of searches
+/// instead of allocating a table for each.
+pub fn minimax_with_table(board: &Board, me: Turn, depth: i32, tt: &mut TranspositionTable) -> i32 {
+    let mut board = board.clone();
+    let mut search = Search::new(&TableEvaluator, tt);
+    let hash = board.zobrist_hash();
+    let score = search.alpha_beta(&mut board, hash, depth, -INFINITY, INFINITY);
+
+    if board.turn == me {
+        score
+    } else {
+        -score
     }
+}
+
+/// Search `board` deeper and deeper until `max_depth` is reached or
+/// `budget` runs out, and report the best move of the deepest
+/// completed iteration.
+///
+/// Leaves are scored by `evaluator`.
+///
+/// The first iteration is always completed regardless of `budget`,
+/// so a move is returned whenever the player to move has one.
+pub fn iterative_deepening(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    max_depth: i32,
+    budget: Option<Duration>,
+) -> Option<SearchReport> {
+    let mut tt = TranspositionTable::default();
+    iterative_deepening_with_table(board, evaluator, max_depth, budget, &mut tt)
+}
+
+/// `iterative_deepening` with `tt` as `minimax_with_table`.
+pub fn iterative_deepening_with_table(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    max_depth: i32,
+    budget: Option<Duration>,
+    tt: &mut TranspositionTable,
+) -> Option<SearchReport> {
+    let mut search = Search::new(evaluator, tt);
+    let deadline = budget.map(utils::deadline_after);
+
+    search.deepen(board, distinct_moves(board), 1, max_depth, deadline)
+}
+
+/// `iterative_deepening` which breaks ties between root moves of the
+/// same score at random by `rng`.
+pub fn iterative_deepening_shuffled(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    max_depth: i32,
+    budget: Option<Duration>,
+    rng: &mut impl Rng,
+) -> Option<SearchReport> {
+    let mut tt = TranspositionTable::default();
+    iterative_deepening_shuffled_with_table(board, evaluator, max_depth, budget, rng, &mut tt)
+}
 
-    let children = board.children();
+/// `iterative_deepening_shuffled` with `tt` as `minimax_with_table`.
+pub fn iterative_deepening_shuffled_with_table(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    max_depth: i32,
+    budget: Option<Duration>,
+    rng: &mut impl Rng,
+    tt: &mut TranspositionTable,
+) -> Option<SearchReport> {
+    let mut search = Search::new(evaluator, tt);
+    let deadline = budget.map(utils::deadline_after);
+    let mut moves = distinct_moves(board);
+
+    // the first of the best moves is taken.
+    moves.shuffle(rng);
+    search.deepen(board, moves, 1, max_depth, deadline)
+}
 
-    if children.is_empty() {
-        // game over
-        return board.eval_score(me);
+/// `iterative_deepening` by `threads` threads in Lazy SMP.
+///
+/// All threads search the same root sharing a transposition table,
+/// and the result of the main thread is reported.  Helper threads
+/// start from different depths and root moves, so that they fill the
+/// table with results which the main thread needs next.
+///
+/// Ties between root moves are broken by `rng` as
+/// `iterative_deepening_shuffled`.  With one thread, the search is
+/// deterministic for the same `rng`.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub fn parallel_iterative_deepening(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    max_depth: i32,
+    budget: Option<Duration>,
+    threads: usize,
+    rng: &mut impl Rng,
+) -> Option<SearchReport> {
+    let table = SharedTable::default();
+    parallel_iterative_deepening_with_table(
+        board, evaluator, max_depth, budget, threads, rng, &table,
+    )
+}
+
+/// `parallel_iterative_deepening` with `table` as
+/// `minimax_with_table`.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub fn parallel_iterative_deepening_with_table(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    max_depth: i32,
+    budget: Option<Duration>,
+    threads: usize,
+    rng: &mut impl Rng,
+    table: &SharedTable,
+) -> Option<SearchReport> {
+    let stop = AtomicBool::new(false);
+    let mut moves = distinct_moves(board);
+    moves.shuffle(rng);
+    let deadline = budget.map(utils::deadline_after);
+
+    if moves.is_empty() {
+        return None;
     }
 
-    if me == board.turn {
-        for child in &children {
-            let ab = alpha_beta(child, me, depth - 1, alpha, beta);
-            alpha = max(alpha, ab);
+    std::thread::scope(|scope| {
+        let helpers: Vec<_> = (1..threads)
+            .map(|i| {
+                let stop = &stop;
+                let mut moves = moves.clone();
+                let skip = i % moves.len();
+                moves.rotate_left(skip);
+
+                scope.spawn(move || {
+                    let mut search = Search::new(evaluator, table);
+                    search.stop = Some(stop);
+                    search.deepen(board, moves, 1 + (i % 2) as i32, max_depth, None);
+                    search.nodes
+                })
+            })
+            .collect();
+
+        let mut search = Search::new(evaluator, table);
+        let mut best = search.deepen(board, moves, 1, max_depth, deadline);
+        stop.store(true, Ordering::Relaxed);
+
+        for helper in helpers {
+            let nodes = helper.join().unwrap();
+            if let Some(best) = &mut best {
+                best.nodes += nodes;
+            }
+        }
+        best
+    })
+}
+
+/// Score every legal move of `board` by a full-window search of
+/// `max_depth` plies including the move itself, and report them best
+/// first.
+///
+/// With `budget`, the moves are scored deeper and deeper up to
+/// `max_depth` as `iterative_deepening`, and the scores of the deepest
+/// completed iteration are reported.
+pub fn analyze(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    max_depth: i32,
+    budget: Option<Duration>,
+) -> Vec<SearchReport> {
+    let mut tt = TranspositionTable::default();
+    analyze_with_table(board, evaluator, max_depth, budget, &mut tt)
+}
+
+/// `analyze` with `tt` as `minimax_with_table`.
+pub fn analyze_with_table(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    max_depth: i32,
+    budget: Option<Duration>,
+    tt: &mut TranspositionTable,
+) -> Vec<SearchReport> {
+    let mut search = Search::new(evaluator, tt);
+    let deadline = budget.map(utils::deadline_after);
+    // without a budget, shallower iterations would only be thrown away.
+    let first_depth = if budget.is_some() { 1 } else { max_depth };
+    let mut reports = Vec::new();
+
+    for depth in first_depth..=max(max_depth, first_depth) {
+        let scored = search.score_moves(board, depth);
+
+        if search.aborted {
+            break;
+        }
+        let exact = scored.iter().all(|report| report.exact);
+        reports = scored;
+
+        if exact {
+            break;
+        }
+        search.deadline = deadline;
+        if search.is_out_of_time() {
+            break;
+        }
+    }
+    reports.sort_by_key(|report| -report.score);
+    reports
+}
+
+/// Legal moves of `board` except those leading to a position
+/// symmetric to that of an earlier move, such as 3 of the 4 first
+/// moves of the game.
+fn distinct_moves(board: &Board) -> Vec<Move> {
+    let symmetries: Vec<Symmetry> = Symmetry::ALL[1..]
+        .iter()
+        .copied()
+        .filter(|&symmetry| board.transform(symmetry) == *board)
+        .collect();
+    let mut moves: Vec<Move> = Vec::new();
+
+    for mov in board.legal_move_iter() {
+        if !symmetries.iter().any(|s| moves.contains(&s.apply(mov))) {
+            moves.push(mov);
+        }
+    }
+    moves
+}
+
+fn move_to_front(moves: &mut [Move], mov: Move) {
+    if let Some(i) = moves.iter().position(|&m| m == mov) {
+        moves[..=i].rotate_right(1);
+    }
+}
+
+/// Depth stored in the transposition table for a result which no
+/// depth limit has affected; it is valid for any deeper search.
+const UNLIMITED_DEPTH: i32 = i32::MAX;
+
+struct Search<'a, T: Table> {
+    evaluator: &'a dyn Evaluator,
+    tt: T,
+    deadline: Option<f64>,
+    /// Set by another thread to abort the search.
+    stop: Option<&'a AtomicBool>,
+    nodes: u64,
+    aborted: bool,
+    truncated: bool,
+}
+
+impl<'a, T: Table> Search<'a, T> {
+    fn new(evaluator: &'a dyn Evaluator, tt: T) -> Self {
+        Search {
+            evaluator,
+            tt,
+            deadline: None,
+            stop: None,
+            nodes: 0,
+            aborted: false,
+            truncated: false,
+        }
+    }
+
+    fn is_out_of_time(&self) -> bool {
+        if self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed)) {
+            return true;
+        }
+        match self.deadline {
+            Some(deadline) => utils::now_ms() >= deadline,
+            None => false,
+        }
+    }
+
+    /// Iterative deepening over root `moves` from `first_depth`.
+    /// The first iteration ignores `deadline`.
+    fn deepen(
+        &mut self,
+        board: &Board,
+        mut moves: Vec<Move>,
+        first_depth: i32,
+        max_depth: i32,
+        deadline: Option<f64>,
+    ) -> Option<SearchReport> {
+        let mut board = board.clone();
+        let start = utils::now_ms();
+        let mut best = None;
+
+        if moves.is_empty() {
+            return None;
+        }
+
+        for depth in first_depth..=max(max_depth, first_depth) {
+            self.truncated = false;
+            let (mov, score) = self.root(&mut board, &moves, depth);
+
+            if self.aborted {
+                break;
+            }
+            best = Some(SearchReport {
+                mov,
+                score,
+                pv: self.principal_variation(&board, mov, depth),
+                nodes: self.nodes,
+                depth,
+                elapsed: utils::elapsed_since(start),
+                exact: !self.truncated,
+            });
+
+            // no leaf was cut by depth; deeper iterations would be the same.
+            if !self.truncated {
+                break;
+            }
+            // search the best move first in the next iteration.
+            move_to_front(&mut moves, mov);
+            self.deadline = deadline;
+            if self.is_out_of_time() {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Score every legal move of `board` by a full-window search of
+    /// `depth` plies including the move itself.
+    fn score_moves(&mut self, board: &Board, depth: i32) -> Vec<SearchReport> {
+        let mut board = board.clone();
+        let hash = board.zobrist_hash();
+        let mut reports = Vec::new();
+
+        for mov in board.legal_move_iter() {
+            let start = utils::now_ms();
+            let nodes = self.nodes;
+
+            self.truncated = false;
+            let undo = board.make_move(mov);
+            let child_hash = zobrist::update_by_undo(hash, &undo);
+            let score = -self.alpha_beta(&mut board, child_hash, depth - 1, -INFINITY, INFINITY);
+            board.unmake_move(undo);
+
+            if self.aborted {
+                break;
+            }
+            reports.push(SearchReport {
+                mov,
+                score,
+                pv: self.principal_variation(&board, mov, depth),
+                nodes: self.nodes - nodes,
+                depth,
+                elapsed: utils::elapsed_since(start),
+                exact: !self.truncated,
+            });
+        }
+        reports
+    }
+
+    /// Follow best moves in the transposition table from `board`
+    /// after `first`, up to `depth` moves.
+    fn principal_variation(&self, board: &Board, first: Move, depth: i32) -> Vec<Position> {
+        let mut board = board.clone();
+        let mut pv = Vec::new();
+        let mut mov = first;
+
+        while mov != 0 && (pv.len() as i32) < depth {
+            pv.push(Position::new(mov));
+            board.put_stone(mov);
+
+            if board.legal_moves() == 0 {
+                board.pass();
+            }
+            mov = self
+                .tt
+                .probe(board.zobrist_hash())
+                .map_or(0, |entry| entry.best_move & board.legal_moves());
+        }
+        pv
+    }
+
+    fn root(&mut self, board: &mut Board, moves: &[Move], depth: i32) -> (Move, i32) {
+        let hash = board.zobrist_hash();
+        let mut best_score = -INFINITY;
+        let mut best_move = moves[0];
+
+        for &mov in moves {
+            let undo = board.make_move(mov);
+            let child_hash = zobrist::update_by_undo(hash, &undo);
+            let score = -self.alpha_beta(board, child_hash, depth - 1, -INFINITY, -best_score);
+            board.unmake_move(undo);
+
+            if self.aborted {
+                break;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = mov;
+            }
+        }
+        (best_move, best_score)
+    }
+
+    fn alpha_beta(
+        &mut self,
+        board: &mut Board,
+        hash: u64,
+        depth: i32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.nodes & (CLOCK_INTERVAL - 1) == 0 && self.is_out_of_time() {
+            self.aborted = true;
+        }
+        if self.aborted {
+            return 0;
+        }
+
+        if depth <= 0 {
+            self.truncated = true;
+            return self.evaluator.evaluate(board, board.turn);
+        }
+
+        let mut tt_move = 0;
+
+        if let Some(entry) = self.tt.probe(hash) {
+            if entry.depth >= depth {
+                let cutoff = match entry.bound {
+                    Bound::Exact => Some(entry.score.max(alpha).min(beta)),
+                    Bound::Lower if entry.score >= beta => Some(beta),
+                    Bound::Upper if entry.score <= alpha => Some(alpha),
+                    _ => None,
+                };
+                if let Some(score) = cutoff {
+                    if entry.depth != UNLIMITED_DEPTH {
+                        self.truncated = true;
+                    }
+                    return score;
+                }
+            }
+            tt_move = entry.best_move;
+        }
+
+        let moves = board.legal_moves();
+
+        if moves == 0 {
+            if board.is_game_over() {
+                return self.evaluator.final_score(board, board.turn);
+            }
+            let undo = board.make_pass();
+            let child_hash = zobrist::update_by_undo(hash, &undo);
+            let score = -self.alpha_beta(board, child_hash, depth - 1, -beta, -alpha);
+            board.unmake_move(undo);
+            return score;
+        }
+
+        // Search the move from the transposition table first.
+        let tt_move = tt_move & moves;
+        let ordered = MoveIter::new(tt_move).chain(MoveIter::new(moves & !tt_move));
+
+        let outer_truncated = self.truncated;
+        let alpha_orig = alpha;
+        let mut best_move = 0;
+        self.truncated = false;
+
+        for mov in ordered {
+            let undo = board.make_move(mov);
+            let child_hash = zobrist::update_by_undo(hash, &undo);
+            let score = -self.alpha_beta(board, child_hash, depth - 1, -beta, -alpha);
+            board.unmake_move(undo);
+
+            if self.aborted {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = mov;
+            }
             if alpha >= beta {
                 break;
             }
         }
+
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if alpha > alpha_orig {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        let stored_depth = if self.truncated {
+            depth
+        } else {
+            UNLIMITED_DEPTH
+        };
+        self.tt.store(hash, stored_depth, bound, alpha, best_move);
+        self.truncated |= outer_truncated;
+
         alpha
-    } else {
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn iterative_deepening_agrees_with_minimax() {
+        let mut board = Board::new();
+        for pos in &["f5", "d6"] {
+            board.put_stone(Position::from_str(pos).unwrap().as_bits());
+        }
+        let best = iterative_deepening(&board, &TableEvaluator, 4, None).unwrap();
+        let mut child = board.clone();
+        child.put_stone(best.mov);
+
+        assert_eq!(best.depth, 4);
+        assert_eq!(best.score, minimax(&child, board.turn, 3));
+        assert!(!best.exact);
+
+        // the leaf of the principal variation has the score of the search.
+        assert_eq!(best.pv.len(), 4);
+        assert_eq!(best.pv[0], Position::new(best.mov));
+        let mut leaf = board.clone();
+        for pos in &best.pv {
+            assert!(leaf.is_legal_move(pos.as_bits()));
+            leaf.put_stone(pos.as_bits());
+        }
+        assert_eq!(TableEvaluator.evaluate(&leaf, board.turn), best.score);
+    }
+
+    fn plain_alpha_beta(board: &Board, depth: i32, mut alpha: i32, beta: i32) -> i32 {
+        let children = board.children();
+
+        if children.is_empty() {
+            return TableEvaluator.final_score(board, board.turn);
+        }
+        if depth <= 0 {
+            return TableEvaluator.evaluate(board, board.turn);
+        }
         for child in &children {
-            let ab = alpha_beta(child, me, depth - 1, alpha, beta);
-            beta = min(beta, ab);
+            alpha = max(alpha, -plain_alpha_beta(child, depth - 1, -beta, -alpha));
             if alpha >= beta {
                 break;
             }
         }
-        beta
+        alpha
+    }
+
+    #[test]
+    fn transposition_table_keeps_scores() {
+        let mut board = Board::new();
+        let mut tt = TranspositionTable::default();
+
+        while !board.is_game_over() {
+            let expected = plain_alpha_beta(&board, 4, -INFINITY, INFINITY);
+            assert_eq!(minimax(&board, board.turn, 4), expected);
+            // entries of earlier positions are still valid.
+            assert_eq!(minimax_with_table(&board, board.turn, 4, &mut tt), expected);
+            board = board.children().pop().unwrap();
+        }
+    }
+
+    #[test]
+    fn analyze_scores_every_move() {
+        let mut board = Board::new();
+        board.put_stone(Position::from_str("f5").unwrap().as_bits());
+        let reports = analyze(&board, &TableEvaluator, 3, None);
+        let best = iterative_deepening(&board, &TableEvaluator, 3, None).unwrap();
+
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].score, best.score);
+        for report in &reports {
+            let mut child = board.clone();
+            child.put_stone(report.mov);
+            assert_eq!(report.score, minimax(&child, board.turn, 2));
+            assert_eq!(report.pv[0], Position::new(report.mov));
+        }
+        assert!(reports.windows(2).all(|w| w[0].score >= w[1].score));
+
+        let deepened = analyze(&board, &TableEvaluator, 3, Some(Duration::from_secs(60)));
+        let scores = |reports: &[SearchReport]| -> Vec<(Move, i32)> {
+            reports.iter().map(|r| (r.mov, r.score)).collect()
+        };
+        assert_eq!(scores(&deepened), scores(&reports));
+        assert!(deepened.iter().all(|report| report.depth == 3));
+    }
+
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    #[test]
+    fn parallel_search_solves_endgame_exactly() {
+        use crate::board::midgame_board;
+        use crate::endgame::{self, SolveMode};
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        for seed in 0..4_u64 {
+            let board = midgame_board(seed, 10);
+            if board.legal_moves() == 0 {
+                continue;
+            }
+            let expected = endgame::solve(&board, SolveMode::Exact).score;
+
+            let search = |threads| {
+                let mut rng = StdRng::seed_from_u64(seed);
+                parallel_iterative_deepening(&board, &TableEvaluator, 64, None, threads, &mut rng)
+                    .unwrap()
+            };
+            for &threads in &[1, 4] {
+                let best = search(threads);
+                assert!(best.exact);
+                assert_eq!(
+                    best.score,
+                    expected * TableEvaluator::SCALE,
+                    "seed {} threads {}",
+                    seed,
+                    threads
+                );
+            }
+            let (once, again) = (search(1), search(1));
+            assert_eq!((once.mov, once.pv), (again.mov, again.pv));
+        }
+    }
+
+    #[test]
+    fn shuffled_search_breaks_ties_at_random() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        // the first moves are pruned to one by symmetry; after f5,
+        // some replies still score the same at depth 3.
+        let mut board = Board::new();
+        board.put_stone(Position::from_str("f5").unwrap().as_bits());
+        let score = iterative_deepening(&board, &TableEvaluator, 3, None)
+            .unwrap()
+            .score;
+        let mut chosen = Vec::new();
+
+        for seed in 0..16 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let best =
+                iterative_deepening_shuffled(&board, &TableEvaluator, 3, None, &mut rng).unwrap();
+            assert_eq!(best.score, score);
+            if !chosen.contains(&best.mov) {
+                chosen.push(best.mov);
+            }
+        }
+        assert!(chosen.len() > 1);
+    }
+
+    #[test]
+    fn iterative_deepening_returns_move_without_time() {
+        let board = Board::new();
+        let best = iterative_deepening(&board, &TableEvaluator, 60, Some(Duration::from_millis(0)))
+            .unwrap();
+
+        assert!(board.is_legal_move(best.mov));
+        assert_eq!(best.depth, 1);
     }
 }
@@ -0,0 +1,106 @@
+//! OBF (Othello Board Format) lines of test suites, such as:
+//!
+//! ```text
+//! --XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO-- X; G8:+18; H1:+12; H7:+6;
+//! ```
+//!
+//! A position in the notation of `Board::to_notation` is followed by
+//! the scores of moves, best first.  "PS" is a pass.
+//!
+
+use crate::bitboard::Move;
+use crate::board::Board;
+use crate::position::Position;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObfLine {
+    pub board: Board,
+    /// Moves (0 for pass) and their scores, as written.
+    pub scores: Vec<(Move, i32)>,
+}
+
+impl ObfLine {
+    /// Moves of the best score.
+    pub fn best_moves(&self) -> Vec<Move> {
+        let best = self.scores.iter().map(|&(_, score)| score).max();
+
+        self.scores
+            .iter()
+            .filter(|&&(_, score)| Some(score) == best)
+            .map(|&(mov, _)| mov)
+            .collect()
+    }
+}
+
+impl FromStr for ObfLine {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, String> {
+        let mut fields = line.split(';').map(str::trim);
+        let board = fields.next().unwrap_or("").parse()?;
+        let mut scores = Vec::new();
+
+        for field in fields.filter(|field| !field.is_empty()) {
+            let (square, score) = field
+                .split_once(':')
+                .ok_or(format!("invalid move score '{}'", field))?;
+            let square = square.trim().to_lowercase();
+            let mov = if square == "ps" {
+                0
+            } else {
+                Position::from_str(&square)
+                    .map_err(|_| format!("invalid move '{}'", square))?
+                    .as_bits()
+            };
+            let score = score
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid score '{}'", score.trim()))?;
+
+            scores.push((mov, score));
+        }
+        Ok(ObfLine { board, scores })
+    }
+}
+
+impl fmt::Display for ObfLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{};", self.board.to_notation())?;
+
+        for &(mov, score) in &self.scores {
+            let square = if mov == 0 {
+                "PS".to_string()
+            } else {
+                Position::new(mov).to_string().to_uppercase()
+            };
+            write!(f, " {}:{:+};", square, score)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn obf_round_trip() {
+        let text = "--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO-- X; \
+                    G8:+18; H1:+12; H7:+6; A2:+18;";
+        let line: ObfLine = text.parse().unwrap();
+
+        assert_eq!(line.board.count_hole(), 14);
+        assert_eq!(line.scores.len(), 4);
+        assert_eq!(line.best_moves().len(), 2);
+        assert_eq!(line.to_string(), text);
+        assert_eq!(line.to_string().parse(), Ok(line.clone()));
+
+        let bare: ObfLine = line.board.to_notation().parse().unwrap();
+        assert!(bare.scores.is_empty());
+        assert!(format!("{}; Z9:+2;", line.board.to_notation())
+            .parse::<ObfLine>()
+            .is_err());
+    }
+}
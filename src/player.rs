@@ -1,15 +1,32 @@
-use crate::minimax;
+use crate::board::Board;
+use crate::book::Book;
+use crate::endgame::{self, SolveMode};
+use crate::evaluator::{Evaluator, NoisyEvaluator, TableEvaluator};
+use crate::level::Level;
+use crate::minimax::{self, SearchReport};
 use crate::position::Position;
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use crate::transposition::SharedTable;
+use crate::transposition::TranspositionTable;
 use crate::ui_board::UiBoard;
+use crate::utils;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
 use std::fmt;
 use std::io::{self, BufRead, Write};
 use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Action {
     Move(Position),
     Pass,
     GiveUp,
+    /// Take back the last move of the player.
+    Undo,
+    /// Play the move taken back by `Undo` again.
+    Redo,
 }
 
 impl fmt::Display for Action {
@@ -18,6 +35,8 @@ impl fmt::Display for Action {
             Action::Move(pos) => write!(f, "Move {}", pos)?,
             Action::Pass => write!(f, "Pass")?,
             Action::GiveUp => write!(f, "GiveUp")?,
+            Action::Undo => write!(f, "Undo")?,
+            Action::Redo => write!(f, "Redo")?,
         }
         Ok(())
     }
@@ -25,15 +44,96 @@ impl fmt::Display for Action {
 
 pub trait Player {
     fn action(&mut self, board: &UiBoard) -> Action;
+
+    /// Choose an action in the game of `controller`, which players
+    /// with commands may also ask for analysis or to load a game.
+    /// `None` if there's no action to take, such as after loading.
+    fn interact(&mut self, controller: &mut dyn Controller) -> Option<Action> {
+        Some(self.action(controller.board()))
+    }
+
+    /// Report of the search behind the last action, if any.
+    fn last_report(&self) -> Option<&SearchReport> {
+        None
+    }
+}
+
+/// The game a player is in, as seen from `Player::interact`.
+pub trait Controller {
+    /// The game in progress.
+    fn board(&self) -> &UiBoard;
+
+    /// Draw the game in progress again.
+    fn show_board(&self);
+
+    /// Save the game in progress to `path`.
+    fn save(&self, path: &str) -> Result<(), String>;
+
+    /// Replace the game in progress with the game in `path`.
+    fn load(&mut self, path: &str) -> Result<(), String>;
+
+    /// Scores of the best `top` (or all) moves by the engine of the
+    /// game, best first.
+    fn analyze(&self, top: Option<usize>) -> Vec<SearchReport>;
+
+    /// Stop the game without finishing it.
+    fn quit(&mut self);
+}
+
+/// Controller of a bare board, which can't show, save, load nor
+/// analyze games.
+pub(crate) struct BoardOnly<'a>(pub(crate) &'a UiBoard);
+
+impl Controller for BoardOnly<'_> {
+    fn board(&self) -> &UiBoard {
+        self.0
+    }
+
+    fn show_board(&self) {}
+
+    fn save(&self, _path: &str) -> Result<(), String> {
+        Err("no game to save".to_string())
+    }
+
+    fn load(&mut self, _path: &str) -> Result<(), String> {
+        Err("no game to load into".to_string())
+    }
+
+    fn analyze(&self, _top: Option<usize>) -> Vec<SearchReport> {
+        Vec::new()
+    }
+
+    fn quit(&mut self) {}
 }
 
 pub struct HumanPlayer {
-    tty: Box<dyn std::io::BufRead>,
+    /// Input of moves and commands, or `None` for stdin.
+    tty: Option<Box<dyn std::io::BufRead>>,
 }
 
-pub struct RobotPlayer {}
+pub struct RobotPlayer {
+    rng: StdRng,
+}
 
-pub struct CleverRobotPlayer {}
+pub struct CleverRobotPlayer {
+    time_budget: Option<Duration>,
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    threads: usize,
+    /// Table of the searches by more than one thread.
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    shared_table: Option<SharedTable>,
+    /// Kept for every search instead of allocating one each time, and
+    /// cleared before each as the noise of the evaluator changes.
+    tt: RefCell<TranspositionTable>,
+    depth: i32,
+    endgame_holes: u32,
+    noise: i32,
+    blunder_rate: f64,
+    evaluator: Box<dyn Evaluator>,
+    book: Option<Book>,
+    rng: StdRng,
+    last_report: Option<SearchReport>,
+}
 
 impl Default for RobotPlayer {
     fn default() -> Self {
@@ -43,7 +143,15 @@ impl Default for RobotPlayer {
 
 impl RobotPlayer {
     pub fn new() -> Self {
-        RobotPlayer {}
+        RobotPlayer {
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Make the choice of moves reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
     }
 }
 
@@ -54,14 +162,96 @@ impl Default for CleverRobotPlayer {
 }
 
 impl CleverRobotPlayer {
+    /// Deepest iteration tried when searching with a time budget.
+    const MAX_DEPTH: i32 = 64;
+
+    /// Most holes to solve exactly with a time budget, which usually
+    /// takes milliseconds; more holes may take much longer than the
+    /// budget, as the solver can't stop halfway.
+    const BUDGET_ENDGAME_HOLES: u32 = 12;
+
+    /// Create player of `Level::default()`.
     pub fn new() -> Self {
-        CleverRobotPlayer {}
+        CleverRobotPlayer {
+            time_budget: None,
+            #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+            threads: 1,
+            #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+            shared_table: None,
+            tt: RefCell::new(TranspositionTable::default()),
+            depth: 0,
+            endgame_holes: 0,
+            noise: 0,
+            blunder_rate: 0.0,
+            evaluator: Box::new(TableEvaluator),
+            book: None,
+            rng: StdRng::from_entropy(),
+            last_report: None,
+        }
+        .with_level(Level::default())
+    }
+
+    /// Play as strong as `level`.
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.depth = level.depth();
+        self.endgame_holes = level.endgame_holes();
+        self.noise = level.noise();
+        self.blunder_rate = level.blunder_rate();
+        self
+    }
+
+    /// Search `depth` plies while many holes remain, overriding the
+    /// depth of the level.
+    pub fn with_depth(mut self, depth: i32) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Spend about `budget` for each move instead of searching to a
+    /// fixed depth.
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Search by `threads` threads.
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self.shared_table = (self.threads > 1).then(SharedTable::default);
+        self
+    }
+
+    /// Score leaves of the search by `evaluator` instead of the
+    /// default `TableEvaluator`.
+    pub fn with_evaluator(mut self, evaluator: Box<dyn Evaluator>) -> Self {
+        self.evaluator = evaluator;
+        self
+    }
+
+    /// Play the best move of `book` while the position is in it.
+    pub fn with_book(mut self, book: Book) -> Self {
+        self.book = Some(book);
+        self
+    }
+
+    /// Make the noise, blunders and the choice among equally scored
+    /// moves reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
     }
 }
 
 impl HumanPlayer {
     pub fn new(tty: Box<dyn std::io::BufRead>) -> Self {
-        HumanPlayer { tty }
+        HumanPlayer { tty: Some(tty) }
+    }
+
+    /// Player at stdin.  The buffer of stdin is shared by all its
+    /// readers, so two players can take turns at the same terminal.
+    pub fn stdin() -> Self {
+        HumanPlayer { tty: None }
     }
 }
 
@@ -72,8 +262,7 @@ impl Player for RobotPlayer {
         if vec.is_empty() {
             Action::Pass
         } else {
-            let rnd: usize = rand::random();
-            let pos = vec[rnd % vec.len()];
+            let pos = vec[self.rng.gen_range(0, vec.len())];
             Action::Move(pos)
         }
     }
@@ -81,54 +270,354 @@ impl Player for RobotPlayer {
 
 impl Player for CleverRobotPlayer {
     fn action(&mut self, board: &UiBoard) -> Action {
-        let raw_board = board.raw_board();
-        let mut moves = raw_board.legal_moves();
-        let turn = raw_board.turn;
+        let board = board.raw_board();
 
-        if moves == 0 {
-            Action::Pass
+        self.last_report = if self.rng.gen_bool(self.blunder_rate) {
+            self.blunder(board)
         } else {
-            let mut best_score = std::i32::MIN;
-            let mut best_move = 0;
-            let holes = raw_board.count_hole();
-            let depth = if holes <= 14 { 14 } else { 5 };
-
-            while moves != 0 {
-                let mov = 1 << moves.trailing_zeros();
-                let mut child = raw_board.clone();
-                child.put_stone(mov);
-                let score = minimax::minimax(&child, turn, depth);
-
-                if score > best_score {
-                    best_score = score;
-                    best_move = mov;
-                }
-                moves &= !mov;
-            }
-            Action::Move(Position::new(best_move))
+            self.search(board)
+        };
+
+        match &self.last_report {
+            Some(report) => Action::Move(Position::new(report.mov)),
+            None => Action::Pass,
+        }
+    }
+
+    fn last_report(&self) -> Option<&SearchReport> {
+        self.last_report.as_ref()
+    }
+}
+
+impl CleverRobotPlayer {
+    /// Score every legal move of `board` the same way as choosing a
+    /// move (except the book), and report the best `top` of them, best
+    /// first.
+    pub fn analyze(&self, board: &Board, top: Option<usize>) -> Vec<SearchReport> {
+        let mut reports = if board.count_hole() <= self.endgame_holes() {
+            let mut reports: Vec<SearchReport> = board
+                .legal_move_iter()
+                .map(|mov| {
+                    let start = utils::now_ms();
+                    let mut child = board.clone();
+                    child.put_stone(mov);
+                    let solution = endgame::solve(&child, SolveMode::Exact);
+
+                    SearchReport {
+                        mov,
+                        score: -solution.score,
+                        pv: std::iter::once(mov)
+                            .chain(solution.pv)
+                            .map(Position::new)
+                            .collect(),
+                        nodes: solution.nodes,
+                        depth: board.count_hole() as i32,
+                        elapsed: utils::elapsed_since(start),
+                        exact: true,
+                    }
+                })
+                .collect();
+            reports.sort_by_key(|report| -report.score);
+            reports
+        } else {
+            let mut tt = self.tt.borrow_mut();
+            tt.clear();
+            minimax::analyze_with_table(
+                board,
+                self.evaluator.as_ref(),
+                self.max_depth(),
+                self.time_budget,
+                &mut tt,
+            )
+        };
+
+        if let Some(top) = top {
+            reports.truncate(top);
+        }
+        reports
+    }
+
+    /// Holes from which moves are read out to the end.
+    fn endgame_holes(&self) -> u32 {
+        if self.time_budget.is_some() {
+            self.endgame_holes.min(Self::BUDGET_ENDGAME_HOLES)
+        } else {
+            self.endgame_holes
+        }
+    }
+
+    /// Depth limit of the search.  The root move itself counts as a
+    /// ply of iterative deepening.
+    fn max_depth(&self) -> i32 {
+        if self.time_budget.is_some() {
+            Self::MAX_DEPTH
+        } else {
+            self.depth + 1
+        }
+    }
+
+    /// Pick one of the moves other than the best at random.  The best
+    /// is only by a search of 2 plies, which is enough to blunder.
+    fn blunder(&mut self, board: &Board) -> Option<SearchReport> {
+        let tt = self.tt.get_mut();
+        tt.clear();
+        let mut reports = minimax::analyze_with_table(board, self.evaluator.as_ref(), 2, None, tt);
+
+        if reports.len() < 2 {
+            return reports.pop();
+        }
+        let i = self.rng.gen_range(1, reports.len());
+        Some(reports.swap_remove(i))
+    }
+
+    /// Pick a move from the book, the endgame solver or the search.
+    fn search(&mut self, board: &Board) -> Option<SearchReport> {
+        let start = utils::now_ms();
+        let elapsed = || utils::elapsed_since(start);
+
+        // a stale or broken book may have an illegal move.
+        let book_move = self
+            .book
+            .as_ref()
+            .and_then(|book| book.best_move(board))
+            .filter(|book_move| board.is_legal_move(book_move.mov));
+
+        if let Some(book_move) = book_move {
+            return Some(SearchReport {
+                mov: book_move.mov,
+                score: book_move.score,
+                pv: vec![Position::new(book_move.mov)],
+                nodes: 0,
+                depth: 0,
+                elapsed: elapsed(),
+                exact: false,
+            });
+        }
+
+        if board.count_hole() <= self.endgame_holes() {
+            let solution = endgame::solve(board, SolveMode::Exact);
+            let mov = solution.best_move?;
+
+            return Some(SearchReport {
+                mov,
+                score: solution.score,
+                pv: solution.pv.into_iter().map(Position::new).collect(),
+                nodes: solution.nodes,
+                depth: board.count_hole() as i32,
+                elapsed: elapsed(),
+                exact: true,
+            });
+        }
+
+        let max_depth = self.max_depth();
+
+        let noisy = NoisyEvaluator::new(self.evaluator.as_ref(), self.noise, self.rng.gen());
+        let evaluator: &dyn Evaluator = if self.noise > 0 {
+            &noisy
+        } else {
+            self.evaluator.as_ref()
+        };
+
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        if let Some(table) = &self.shared_table {
+            table.clear();
+            return minimax::parallel_iterative_deepening_with_table(
+                board,
+                evaluator,
+                max_depth,
+                self.time_budget,
+                self.threads,
+                &mut self.rng,
+                table,
+            );
+        }
+
+        let tt = self.tt.get_mut();
+        tt.clear();
+        minimax::iterative_deepening_shuffled_with_table(
+            board,
+            evaluator,
+            max_depth,
+            self.time_budget,
+            &mut self.rng,
+            tt,
+        )
+    }
+}
+
+const HUMAN_HELP: &str = r#"Commands:
+  f5: Put a stone on f5
+  pass: Pass when you have no legal move
+  undo, redo: Take back your last move, or play it again
+  hint: Show the best move by the computer
+  eval: Show the scores of every move by the computer
+  history: Show the moves so far
+  board: Show the board again
+  save FILE: Save the game to FILE (transcript or .ggf)
+  load FILE: Continue the game in FILE (transcript, .ggf or .wtb)
+  giveup: Give up the game
+  quit: Stop the game
+  help: Show this message
+"#;
+
+impl HumanPlayer {
+    /// Next line of input, or `None` at the end.
+    fn read_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+
+        let result = match &mut self.tty {
+            Some(tty) => tty.read_line(&mut line),
+            None => io::stdin().read_line(&mut line),
+        };
+
+        match result {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+
+    fn print_scores(controller: &dyn Controller, top: Option<usize>) {
+        let reports = controller.analyze(top);
+
+        if reports.is_empty() {
+            println!("No moves to analyze");
+        }
+        for report in reports {
+            println!(
+                "{} {:+}{}",
+                Position::new(report.mov),
+                report.score,
+                if report.exact { " (exact)" } else { "" }
+            );
+        }
+    }
+
+    fn print_history(board: &UiBoard) {
+        let moves: Vec<String> = board
+            .history()
+            .iter()
+            .zip(1..)
+            .map(|(hist, ply)| match hist.action {
+                Action::Move(pos) => format!("{}.{}", ply, pos),
+                _ => format!("{}.pass", ply),
+            })
+            .collect();
+
+        if moves.is_empty() {
+            println!("No moves yet");
+        } else {
+            println!("{}", moves.join(" "));
         }
     }
 }
 
 impl Player for HumanPlayer {
-    fn action(&mut self, _board: &UiBoard) -> Action {
+    fn action(&mut self, board: &UiBoard) -> Action {
+        // without a game to stop, quit is to give up.
+        self.interact(&mut BoardOnly(board))
+            .unwrap_or(Action::GiveUp)
+    }
+
+    fn interact(&mut self, controller: &mut dyn Controller) -> Option<Action> {
         loop {
             print!("Move: ");
             io::stdout().flush().unwrap();
 
-            let mut line = String::new();
-            self.tty.read_line(&mut line).unwrap();
-            line = line.trim_end().to_string();
+            let line = match self.read_line() {
+                Some(line) => line,
+                None => return Some(Action::GiveUp), // end of input
+            };
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or("").to_lowercase();
+            let file = words.next();
 
-            if &line == "pass" {
-                return Action::Pass;
-            } else if &line == "giveup" {
-                return Action::GiveUp;
-            } else if let Ok(pos) = Position::from_str(&line) {
-                return Action::Move(pos);
+            match (&*command, file) {
+                ("", _) => (),
+                ("pass", None) => return Some(Action::Pass),
+                ("giveup", None) => return Some(Action::GiveUp),
+                ("undo", None) => return Some(Action::Undo),
+                ("redo", None) => return Some(Action::Redo),
+                ("hint", None) => Self::print_scores(controller, Some(1)),
+                ("eval", None) => Self::print_scores(controller, None),
+                ("history", None) => Self::print_history(controller.board()),
+                ("board", None) => controller.show_board(),
+                ("help", None) => print!("{}", HUMAN_HELP),
+                ("quit", None) => {
+                    controller.quit();
+                    return None;
+                }
+                ("save", Some(path)) => match controller.save(path) {
+                    Ok(()) => println!("Saved to {}", path),
+                    Err(err) => println!("{}", err),
+                },
+                ("load", Some(path)) => match controller.load(path) {
+                    Ok(()) => return None,
+                    Err(err) => println!("{}", err),
+                },
+                ("save", None) | ("load", None) => println!("{} needs FILE", command),
+                (square, None) => match Position::from_str(square) {
+                    Ok(pos) => return Some(Action::Move(pos)),
+                    Err(_) => println!("Unknown command '{}'; type 'help' for commands", line),
+                },
+                _ => println!("Unknown command '{}'; type 'help' for commands", line),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Moves of a game between `black` and `white` from the beginning.
+    fn play(black: &mut impl Player, white: &mut impl Player) -> Vec<Position> {
+        let mut board = UiBoard::new();
+        let mut moves = Vec::new();
+
+        while let Some(turn) = board.whatnow() {
+            let action = if turn.is_black() {
+                black.action(&board)
             } else {
-                print!("Invalid '{}' ", &line);
+                white.action(&board)
+            };
+            match action {
+                Action::Move(pos) => {
+                    board.put_stone(pos).unwrap();
+                    moves.push(pos);
+                }
+                Action::Pass => {
+                    board.pass().unwrap();
+                }
+                _ => break,
             }
         }
+        moves
+    }
+
+    #[test]
+    fn seeded_players_are_reproducible() {
+        let game = |seed| {
+            let mut black = RobotPlayer::new().with_seed(seed);
+            let mut white = CleverRobotPlayer::new()
+                .with_level(Level::Beginner)
+                .with_seed(seed);
+            play(&mut black, &mut white)
+        };
+
+        assert_eq!(game(1), game(1));
+        assert_ne!(game(1), game(2));
+    }
+
+    #[test]
+    fn human_commands_other_than_actions_ask_again() {
+        let human = |input: &'static str| HumanPlayer::new(Box::new(io::Cursor::new(input)));
+        let board = UiBoard::new();
+
+        let mut player = human("hint\nhistory\nboard\nbogus\nload\nload nothing\nsave x\nF5\n");
+        let pos = Position::from_str("f5").unwrap();
+        assert_eq!(player.action(&board), Action::Move(pos));
+        assert_eq!(human("  \nundo\n").action(&board), Action::Undo);
+        assert_eq!(human("quit\n").action(&board), Action::GiveUp);
+        assert_eq!(human("").action(&board), Action::GiveUp);
     }
 }
@@ -1,5 +1,6 @@
 //! Position
 
+use crate::bitboard::Symmetry;
 use std::fmt;
 use std::str::FromStr;
 
@@ -19,7 +20,7 @@ impl FromStr for Position {
         let col: i32 = (ascii[0] as i32) - ('a' as i32);
         let row: i32 = (ascii[1] as i32) - ('1' as i32);
 
-        if 0 <= col && col <= 7 && 0 <= row && row <= 7 {
+        if (0..=7).contains(&col) && (0..=7).contains(&row) {
             Ok(Position((1 << 63) >> (row * 8 + col)))
         } else {
             Err(())
@@ -33,7 +34,7 @@ impl Position {
     }
 
     pub fn from_xy(x: i32, y: i32) -> Option<Self> {
-        if 0 <= x && x <= 7 && 0 <= y && y <= 7 {
+        if (0..=7).contains(&x) && (0..=7).contains(&y) {
             Some(Position((1 << 63) >> (y * 8 + x)))
         } else {
             None
@@ -59,6 +60,11 @@ impl Position {
     pub fn y(&self) -> i32 {
         (self.0.leading_zeros() / 8) as i32
     }
+
+    /// Rotated or mirrored position.
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        Position(symmetry.apply(self.0))
+    }
 }
 
 impl fmt::Display for Position {
@@ -0,0 +1,11 @@
+//! Screens of games on the terminal.
+//!
+
+use crate::ui_board::UiBoard;
+
+pub trait Screen {
+    fn update_screen(&self, board: &UiBoard);
+
+    /// Show `message`, such as a report of search or an error.
+    fn message(&self, message: &str);
+}
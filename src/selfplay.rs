@@ -0,0 +1,213 @@
+//! Self-play data generation for tuning evaluators.
+//!
+//! Engines play against each other from randomized openings, and
+//! every position of the game is recorded with the final disc
+//! differential.  `trainer` fits evaluation weights to the records.
+//!
+//! Records are stored as text, one position in a line:
+//!
+//! ```text
+//! <black bits in hex> <white bits in hex> <turn: b|w> <final black - white>
+//! ```
+//!
+
+use crate::board::{Board, Turn};
+use crate::endgame::{self, SolveMode};
+use crate::evaluator::Evaluator;
+use crate::minimax;
+use crate::transposition::TranspositionTable;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::{self, BufRead, Write};
+
+/// A position and the outcome of the game it appeared in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub board: Board,
+    /// Final disc differential of the game seen from black.
+    pub score: i32,
+}
+
+impl Record {
+    /// Final disc differential seen from `turn`.
+    pub fn score_for(&self, turn: Turn) -> i32 {
+        if turn.is_black() {
+            self.score
+        } else {
+            -self.score
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SelfPlayConfig {
+    /// Number of games to play.
+    pub games: u32,
+    /// Number of random moves at the beginning of each game.
+    pub random_plies: u32,
+    /// Search depth of the engines.
+    pub depth: i32,
+    /// Solve the game exactly once this many holes or fewer remain.
+    pub solve_holes: u32,
+    pub seed: u64,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        SelfPlayConfig {
+            games: 100,
+            random_plies: 8,
+            depth: 4,
+            solve_holes: 12,
+            seed: 0,
+        }
+    }
+}
+
+/// Play `config.games` games, and pass the records of each game to `f`.
+pub fn generate(config: &SelfPlayConfig, evaluator: &dyn Evaluator, mut f: impl FnMut(&[Record])) {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut tt = TranspositionTable::default();
+
+    for _ in 0..config.games {
+        f(&play_game(config, evaluator, &mut rng, &mut tt));
+    }
+}
+
+/// Play a game, and return its positions with the final outcome.
+///
+/// `tt` is cleared and reused for the search of each move.
+pub fn play_game(
+    config: &SelfPlayConfig,
+    evaluator: &dyn Evaluator,
+    rng: &mut StdRng,
+    tt: &mut TranspositionTable,
+) -> Vec<Record> {
+    let mut board = Board::new();
+    let mut boards = Vec::new();
+    let mut ply = 0;
+
+    while !board.is_game_over() {
+        let moves: Vec<u64> = board.legal_move_iter().collect();
+
+        if moves.is_empty() {
+            board.pass();
+            continue;
+        }
+        boards.push(board.clone());
+
+        let mov = if ply < config.random_plies {
+            moves[rng.gen_range(0, moves.len())]
+        } else if board.count_hole() <= config.solve_holes {
+            endgame::solve(&board, SolveMode::Exact)
+                .best_move
+                .unwrap_or(moves[0])
+        } else {
+            tt.clear();
+            minimax::iterative_deepening_shuffled_with_table(
+                &board,
+                evaluator,
+                config.depth,
+                None,
+                rng,
+                tt,
+            )
+            .map_or(moves[0], |best| best.mov)
+        };
+        board.put_stone(mov);
+        ply += 1;
+    }
+
+    let score = board.final_score(Turn::Black);
+    boards
+        .into_iter()
+        .map(|board| Record { board, score })
+        .collect()
+}
+
+pub fn write_records(writer: &mut impl Write, records: &[Record]) -> io::Result<()> {
+    for record in records {
+        let turn = if record.board.is_black_turn() {
+            'b'
+        } else {
+            'w'
+        };
+        writeln!(
+            writer,
+            "{:016x} {:016x} {} {}",
+            record.board.black, record.board.white, turn, record.score
+        )?;
+    }
+    Ok(())
+}
+
+pub fn read_records(reader: impl BufRead) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+
+    for (lineno, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_record(&line) {
+            Some(record) => records.push(record),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid record at line {}", lineno + 1),
+                ))
+            }
+        }
+    }
+    Ok(records)
+}
+
+fn parse_record(line: &str) -> Option<Record> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    if words.len() != 4 {
+        return None;
+    }
+    let black = u64::from_str_radix(words[0], 16).ok()?;
+    let white = u64::from_str_radix(words[1], 16).ok()?;
+    let turn = match words[2] {
+        "b" => Turn::Black,
+        "w" => Turn::White,
+        _ => return None,
+    };
+    let score = words[3].parse().ok()?;
+
+    if black & white != 0 {
+        return None;
+    }
+    Some(Record {
+        board: Board { black, white, turn },
+        score,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::TableEvaluator;
+
+    #[test]
+    fn records_round_trip() {
+        let config = SelfPlayConfig {
+            games: 2,
+            depth: 1,
+            solve_holes: 0,
+            ..SelfPlayConfig::default()
+        };
+        let mut records = Vec::new();
+        generate(&config, &TableEvaluator, |game| {
+            assert!(game.iter().all(|r| r.score == game[0].score));
+            records.extend_from_slice(game);
+        });
+
+        let mut text = Vec::new();
+        write_records(&mut text, &records).unwrap();
+        assert_eq!(read_records(&text[..]).unwrap(), records);
+        assert!(read_records(&b"0 0 x 1\n"[..]).is_err());
+    }
+}
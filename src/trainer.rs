@@ -0,0 +1,117 @@
+//! Fit `PatternEvaluator` weights to self-play records.
+//!
+//! Weights are fitted by least squares with stochastic gradient
+//! descent: the sum of the weights of a position should predict the
+//! final disc differential of the game seen from the player to move.
+//! Every record is also used from the opponent's side.
+//!
+
+use crate::evaluator::PatternEvaluator;
+use crate::selfplay::Record;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+#[derive(Debug, Clone)]
+pub struct TrainConfig {
+    /// Number of passes over all records.
+    pub epochs: u32,
+    /// Step size of gradient descent in discs.
+    pub learning_rate: f32,
+    /// Seed to shuffle records.
+    pub seed: u64,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        TrainConfig {
+            epochs: 20,
+            learning_rate: 0.005,
+            seed: 0,
+        }
+    }
+}
+
+/// A position as the indices of its features and the target score.
+struct Sample {
+    features: Vec<u32>,
+    target: f32,
+}
+
+/// Fit weights to `records`.
+///
+/// `progress` is called after each epoch with the epoch number and
+/// the root-mean-square error in discs.
+pub fn train(
+    records: &[Record],
+    config: &TrainConfig,
+    mut progress: impl FnMut(u32, f64),
+) -> PatternEvaluator {
+    let mut evaluator = PatternEvaluator::new();
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut samples = Vec::with_capacity(records.len() * 2);
+
+    for record in records {
+        for &turn in &[record.board.turn, record.board.turn.opposit()] {
+            let features = evaluator
+                .feature_indices(&record.board, turn)
+                .into_iter()
+                .map(|i| i as u32)
+                .collect();
+            let target = record.score_for(turn) as f32;
+            samples.push(Sample { features, target });
+        }
+    }
+
+    let mut weights = vec![0.0_f32; evaluator.weights_mut().len()];
+
+    for epoch in 1..=config.epochs {
+        let mut squared_error = 0.0;
+        samples.shuffle(&mut rng);
+
+        for sample in &samples {
+            let predict: f32 = sample.features.iter().map(|&i| weights[i as usize]).sum();
+            let error = sample.target - predict;
+            let step = config.learning_rate * error;
+
+            for &i in &sample.features {
+                weights[i as usize] += step;
+            }
+            squared_error += (error * error) as f64;
+        }
+        progress(epoch, (squared_error / samples.len().max(1) as f64).sqrt());
+    }
+
+    for (w, &trained) in evaluator.weights_mut().iter_mut().zip(&weights) {
+        *w = (trained * PatternEvaluator::SCALE as f32).round() as i32;
+    }
+    evaluator
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::{Board, Turn};
+    use crate::evaluator::Evaluator;
+
+    #[test]
+    fn trained_weights_predict_outcome() {
+        let board = Board::new();
+        let mut child = board.clone();
+        child.put_stone(board.legal_move_iter().next().unwrap());
+        let records = vec![Record {
+            board: child.clone(),
+            score: 10,
+        }];
+        let config = TrainConfig {
+            epochs: 100,
+            ..TrainConfig::default()
+        };
+        let mut errors = Vec::new();
+        let evaluator = train(&records, &config, |_, e| errors.push(e));
+
+        assert!(errors.last().unwrap() < &errors[0]);
+        let predict = evaluator.evaluate(&child, Turn::Black) / PatternEvaluator::SCALE;
+        assert!((predict - 10).abs() <= 1, "predict {}", predict);
+    }
+}
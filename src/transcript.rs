@@ -0,0 +1,134 @@
+//! Game transcripts: moves as concatenated coordinates such as
+//! "f5d6c3d3c4".
+//!
+//! A pass is written as "pa" in explicit style, or left out in
+//! implicit style because it is forced anyway.  On parsing, both
+//! styles are accepted, as well as upper case coordinates and white
+//! space between moves.
+//!
+
+use crate::board::Board;
+use crate::player::Action;
+use crate::position::Position;
+use crate::ui_board::UiBoard;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Notation of a pass in explicit style.
+pub const PASS: &str = "pa";
+
+/// Error in a transcript at `ply` (1 for the first token).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptError {
+    pub ply: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ply {}: {}", self.ply, self.reason)
+    }
+}
+
+impl Error for TranscriptError {}
+
+/// Transcript of the game on `board`, with passes written as `PASS`
+/// if `explicit_passes`.  `None` if the game doesn't start from the
+/// initial position, which a transcript can't tell.
+pub fn emit(board: &UiBoard, explicit_passes: bool) -> Option<String> {
+    if *board.start_board() != Board::new() {
+        return None;
+    }
+    let mut transcript = String::new();
+
+    for history in board.history() {
+        match history.action {
+            Action::Move(pos) => transcript.push_str(&pos.to_string()),
+            Action::Pass if explicit_passes => transcript.push_str(PASS),
+            _ => (),
+        }
+    }
+    Some(transcript)
+}
+
+/// Replay `transcript` from the initial position, checking every move.
+pub fn parse(transcript: &str) -> Result<UiBoard, TranscriptError> {
+    let chars: Vec<char> = transcript
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let mut board = UiBoard::new();
+    // Passes made by `board` itself after the last move, which an
+    // explicit transcript repeats.
+    let mut passes = 0;
+
+    for (chunk, ply) in chars.chunks(2).zip(1..) {
+        let error = |reason: String| TranscriptError { ply, reason };
+        let token: String = chunk.iter().collect();
+
+        if chunk.len() < 2 {
+            return Err(error(format!("incomplete move '{}'", token)));
+        }
+        if board.is_game_over() {
+            return Err(error(format!("'{}' after the end of the game", token)));
+        }
+        if token == PASS || token == "--" {
+            if passes == 0 {
+                return Err(error("pass while having a legal move".to_string()));
+            }
+            passes -= 1;
+            continue;
+        }
+
+        let pos = Position::from_str(&token)
+            .map_err(|_| error(format!("'{}' is not a square", token)))?;
+        let before = board.history().len();
+
+        board
+            .put_stone(pos)
+            .map_err(|_| error(format!("{} is not a legal move", pos)))?;
+        passes = board.history().len() - before - 1;
+    }
+    Ok(board)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transcript_round_trip() {
+        // black has to pass after the 8th move.
+        let moves = "e6f6g6g7c4h6h8f8d6";
+        let board = parse(moves).unwrap();
+        let explicit = emit(&board, true).unwrap();
+
+        assert_eq!(explicit, "e6f6g6g7c4h6h8f8pad6");
+        assert_eq!(emit(&board, false).unwrap(), moves);
+        assert_eq!(parse(&explicit).unwrap().raw_board(), board.raw_board());
+        assert_eq!(
+            parse(&moves.to_uppercase()).unwrap().raw_board(),
+            board.raw_board()
+        );
+        assert_eq!(
+            parse("f5 d6 c3").map(|b| emit(&b, false)),
+            Ok(Some("f5d6c3".into()))
+        );
+
+        let mut start = Board::new();
+        start.put_stone(Position::from_str("f5").unwrap().as_bits());
+        assert_eq!(emit(&UiBoard::from_board(start), false), None);
+    }
+
+    #[test]
+    fn errors_tell_ply_and_reason() {
+        let error = |transcript| parse(transcript).err().unwrap();
+
+        assert_eq!(error("f5d6f5").to_string(), "ply 3: f5 is not a legal move");
+        assert_eq!(error("f5z9").ply, 2);
+        assert_eq!(error("f5d").ply, 2);
+        assert_eq!(error("f5pa").ply, 2);
+    }
+}
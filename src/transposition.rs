@@ -0,0 +1,290 @@
+//! Transposition table.
+//!
+//! Fixed-size hash table indexed by the Zobrist hash of `Board`.
+//! It remembers the results of searched nodes, so that positions
+//! reached again through different move orders need not be searched
+//! twice, and the best move found last time can be tried first.
+//!
+//! `SharedTable` is a lock-free variant shared by threads of a
+//! parallel search, with the 'parallel' feature.
+//!
+
+use crate::bitboard::Move;
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How `Entry::score` relates to the true score of the node.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The true score is greater than or equal to the score (fail-high).
+    Lower,
+    /// The true score is less than or equal to the score (fail-low).
+    Upper,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Entry {
+    pub key: u64,
+    /// Remaining depth of the search which produced this entry.
+    pub depth: i32,
+    pub bound: Bound,
+    pub score: i32,
+    /// Best (or refutation) move; 0 if unknown.
+    pub best_move: Move,
+}
+
+/// Storage of the results of searched nodes.
+pub trait Table {
+    fn probe(&self, key: u64) -> Option<Entry>;
+
+    /// Store the result of a node.
+    fn store(&mut self, key: u64, depth: i32, bound: Bound, score: i32, best_move: Move);
+}
+
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    mask: usize,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_BITS)
+    }
+}
+
+impl TranspositionTable {
+    /// 2^18 entries of 32 bytes; 8MB.
+    pub const DEFAULT_BITS: u32 = 18;
+
+    /// Create table with 2^`bits` entries.
+    pub fn new(bits: u32) -> Self {
+        let size = 1 << bits;
+        TranspositionTable {
+            entries: vec![None; size],
+            mask: size - 1,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry = None;
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        match self.entries[key as usize & self.mask] {
+            Some(entry) if entry.key == key => Some(entry),
+            _ => None,
+        }
+    }
+
+    /// Store the result of a node.
+    ///
+    /// An entry of the same position searched deeper is kept,
+    /// entries of other positions are always replaced.
+    pub fn store(&mut self, key: u64, depth: i32, bound: Bound, score: i32, best_move: Move) {
+        let slot = &mut self.entries[key as usize & self.mask];
+
+        if let Some(old) = slot {
+            if old.key == key && old.depth > depth {
+                return;
+            }
+        }
+        *slot = Some(Entry {
+            key,
+            depth,
+            bound,
+            score,
+            best_move,
+        });
+    }
+}
+
+impl Table for TranspositionTable {
+    fn probe(&self, key: u64) -> Option<Entry> {
+        TranspositionTable::probe(self, key)
+    }
+
+    fn store(&mut self, key: u64, depth: i32, bound: Bound, score: i32, best_move: Move) {
+        TranspositionTable::store(self, key, depth, bound, score, best_move)
+    }
+}
+
+impl Table for &mut TranspositionTable {
+    fn probe(&self, key: u64) -> Option<Entry> {
+        TranspositionTable::probe(self, key)
+    }
+
+    fn store(&mut self, key: u64, depth: i32, bound: Bound, score: i32, best_move: Move) {
+        TranspositionTable::store(self, key, depth, bound, score, best_move)
+    }
+}
+
+////////////////////////////////////////////////////////////////
+// SharedTable
+
+/// Transposition table shared by threads without locks.
+///
+/// Each entry is packed into a data word, and stored with the key
+/// XORed by the data.  An entry torn by writes of two threads fails
+/// the key check, and is just missed.
+///
+/// Depths are saturated to `i16`, and `i16::MAX` is read back as
+/// `i32::MAX`.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub struct SharedTable {
+    entries: Vec<(AtomicU64, AtomicU64)>,
+    mask: usize,
+}
+
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+impl Default for SharedTable {
+    fn default() -> Self {
+        Self::new(TranspositionTable::DEFAULT_BITS)
+    }
+}
+
+/// Set in data of used entries.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+const VALID: u64 = 1 << 63;
+
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+impl SharedTable {
+    /// Create table with 2^`bits` entries.
+    pub fn new(bits: u32) -> Self {
+        let size = 1 << bits;
+        SharedTable {
+            entries: (0..size)
+                .map(|_| (AtomicU64::new(0), AtomicU64::new(0)))
+                .collect(),
+            mask: size - 1,
+        }
+    }
+
+    pub fn clear(&self) {
+        for (checked_key, data) in self.entries.iter() {
+            checked_key.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let (checked_key, data) = &self.entries[key as usize & self.mask];
+        let data = data.load(Ordering::Relaxed);
+
+        if data & VALID == 0 || checked_key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        Some(unpack(key, data))
+    }
+
+    /// Store the result of a node; the same policy as
+    /// `TranspositionTable::store`.
+    pub fn store(&self, key: u64, depth: i32, bound: Bound, score: i32, best_move: Move) {
+        if let Some(old) = self.probe(key) {
+            if old.depth > depth {
+                return;
+            }
+        }
+        let (checked_key, slot) = &self.entries[key as usize & self.mask];
+        let data = pack(depth, bound, score, best_move);
+
+        checked_key.store(key ^ data, Ordering::Relaxed);
+        slot.store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+impl Table for &SharedTable {
+    fn probe(&self, key: u64) -> Option<Entry> {
+        SharedTable::probe(self, key)
+    }
+
+    fn store(&mut self, key: u64, depth: i32, bound: Bound, score: i32, best_move: Move) {
+        SharedTable::store(self, key, depth, bound, score, best_move)
+    }
+}
+
+/// score: bits 0-31, depth: 32-47, bound: 48-49,
+/// move (index + 1, or 0): 50-56, `VALID`: 63.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+fn pack(depth: i32, bound: Bound, score: i32, best_move: Move) -> u64 {
+    let depth = depth.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    let bound = match bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    let mov = if best_move == 0 {
+        0
+    } else {
+        best_move.trailing_zeros() as u64 + 1
+    };
+
+    VALID | mov << 50 | bound << 48 | (depth as u16 as u64) << 32 | score as u32 as u64
+}
+
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+fn unpack(key: u64, data: u64) -> Entry {
+    let depth = match (data >> 32) as u16 as i16 {
+        i16::MAX => i32::MAX,
+        depth => depth as i32,
+    };
+    let bound = match (data >> 48) & 3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let best_move = match (data >> 50) & 0x7f {
+        0 => 0,
+        mov => 1 << (mov - 1),
+    };
+
+    Entry {
+        key,
+        depth,
+        bound,
+        score: data as u32 as i32,
+        best_move,
+    }
+}
+
+#[cfg(all(test, feature = "parallel", not(target_arch = "wasm32")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shared_table_keeps_entries() {
+        let table = SharedTable::new(4);
+        let entries = [
+            (
+                0x1234_5678_9abc_def0,
+                i32::MAX,
+                Bound::Exact,
+                -1_000_000,
+                1 << 63,
+            ),
+            (0x0fed_cba9_8765_4321, 7, Bound::Lower, 1_000_000, 1),
+            (0x1111_1111_1111_1110, -3, Bound::Upper, 0, 0),
+        ];
+
+        for &(key, depth, bound, score, best_move) in &entries {
+            table.store(key, depth, bound, score, best_move);
+            assert_eq!(
+                table.probe(key),
+                Some(Entry {
+                    key,
+                    depth,
+                    bound,
+                    score,
+                    best_move,
+                })
+            );
+        }
+        // a colliding key replaced the first entry.
+        assert_eq!(table.probe(0x1234_5678_9abc_def0), None);
+        assert_eq!(table.probe(0x0fed_cba9_8765_4322), None);
+    }
+}
@@ -0,0 +1,370 @@
+//! Full-screen UI on the terminal: the board with a cursor moved by
+//! arrow keys, a side panel of the score and moves, and a status line.
+//!
+//! The screen is drawn by raw escape codes, and `stty` lets us read
+//! each key as it is typed.
+//!
+
+use crate::board::Turn;
+use crate::player::{Action, BoardOnly, Controller, Player};
+use crate::position::Position;
+use crate::screen::Screen;
+use crate::ui_board::{Color, UiBoard};
+use std::cell::{Cell, RefCell};
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+
+const KEYS: &str = "Arrows: move  Enter: put  p: pass  u: undo  r: redo  h: hint  e: eval  q: quit";
+
+const PANEL_COLUMN: i32 = 38;
+const STATUS_ROW: i32 = 11;
+/// Lines of the move list in the side panel.
+const MOVE_LINES: usize = 6;
+const MOVES_PER_LINE: usize = 4;
+
+const RESET: &str = "\x1b[0m";
+const BOARD: &str = "\x1b[42m";
+const LAST_MOVE: &str = "\x1b[43m";
+const FLIPPED: &str = "\x1b[46m";
+const BLACK: &str = "\x1b[30m";
+const WHITE: &str = "\x1b[97m";
+
+pub struct TuiScreen {
+    cursor: Cell<Position>,
+    status: RefCell<String>,
+    _raw_mode: RawMode,
+}
+
+impl TuiScreen {
+    /// Take over the terminal of stdin and stdout until dropped.
+    pub fn new() -> io::Result<Self> {
+        let raw_mode = RawMode::enter()?;
+        print!("\x1b[?25l"); // hide the cursor
+
+        Ok(TuiScreen {
+            cursor: Cell::new(Position::from_xy(3, 3).unwrap()),
+            status: RefCell::new(String::new()),
+            _raw_mode: raw_mode,
+        })
+    }
+
+    pub fn cursor(&self) -> Position {
+        self.cursor.get()
+    }
+
+    pub fn set_cursor(&self, pos: Position) {
+        self.cursor.set(pos);
+    }
+
+    /// Move the cursor by (`dx`, `dy`), wrapping around the edges.
+    pub fn move_cursor(&self, dx: i32, dy: i32) {
+        let cursor = self.cursor();
+        let x = (cursor.x() + dx).rem_euclid(8);
+        let y = (cursor.y() + dy).rem_euclid(8);
+
+        self.set_cursor(Position::from_xy(x, y).unwrap());
+    }
+}
+
+impl Screen for TuiScreen {
+    fn update_screen(&self, board: &UiBoard) {
+        print!("{}", render(board, self.cursor(), &self.status.borrow()));
+        io::stdout().flush().unwrap();
+    }
+
+    fn message(&self, message: &str) {
+        *self.status.borrow_mut() = message.to_string();
+        print!("{}\x1b[K{}", locate(STATUS_ROW, 1), message);
+        io::stdout().flush().unwrap();
+    }
+}
+
+impl Drop for TuiScreen {
+    fn drop(&mut self) {
+        // leave the last screen, and show the cursor below it.
+        print!("{}\x1b[?25h", locate(STATUS_ROW + 2, 1));
+        io::stdout().flush().unwrap();
+    }
+}
+
+/// Player at the keyboard of `TuiScreen`.
+pub struct TuiPlayer {
+    screen: Rc<TuiScreen>,
+}
+
+impl TuiPlayer {
+    pub fn new(screen: Rc<TuiScreen>) -> Self {
+        TuiPlayer { screen }
+    }
+
+    /// Show `label` and the scores of the best `top` moves, and move
+    /// the cursor to the best.
+    fn print_scores(&self, controller: &dyn Controller, label: &str, top: Option<usize>) {
+        let reports = controller.analyze(top);
+        let scores: Vec<String> = reports
+            .iter()
+            .map(|report| format!("{} {:+}", Position::new(report.mov), report.score))
+            .collect();
+
+        match reports.first() {
+            Some(best) => {
+                self.screen.set_cursor(Position::new(best.mov));
+                self.screen
+                    .message(&format!("{}: {}", label, scores.join(", ")));
+            }
+            None => self.screen.message("No moves to analyze"),
+        }
+    }
+}
+
+impl Player for TuiPlayer {
+    fn action(&mut self, board: &UiBoard) -> Action {
+        // without a game to stop, quit is to give up.
+        self.interact(&mut BoardOnly(board))
+            .unwrap_or(Action::GiveUp)
+    }
+
+    fn interact(&mut self, controller: &mut dyn Controller) -> Option<Action> {
+        let board = controller.board();
+
+        if !board.is_legal_move(self.screen.cursor()) {
+            if let Some(pos) = board.legal_moves().last() {
+                self.screen.set_cursor(pos);
+            }
+        }
+
+        loop {
+            self.screen.update_screen(controller.board());
+
+            match read_key() {
+                Key::Up => self.screen.move_cursor(0, -1),
+                Key::Down => self.screen.move_cursor(0, 1),
+                Key::Left => self.screen.move_cursor(-1, 0),
+                Key::Right => self.screen.move_cursor(1, 0),
+                Key::Enter => return Some(Action::Move(self.screen.cursor())),
+                Key::Char('p') => return Some(Action::Pass),
+                Key::Char('u') => return Some(Action::Undo),
+                Key::Char('r') => return Some(Action::Redo),
+                Key::Char('h') => self.print_scores(controller, "Hint", Some(1)),
+                Key::Char('e') => self.print_scores(controller, "Scores", None),
+                Key::Char('q') | Key::Quit => {
+                    controller.quit();
+                    return None;
+                }
+                _ => self.screen.message(&format!("Unknown key; {}", KEYS)),
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////
+// Private
+
+/// Terminal mode to read keys without echo, restored on drop.
+///
+/// Reads time out after 0.1 seconds, to tell a lone ESC from an
+/// escape sequence.  Ctrl-C still interrupts, even a long search.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enter() -> io::Result<Self> {
+        let saved = stty(&["-g"])?.trim().to_string();
+        stty(&["-icanon", "-echo", "min", "0", "time", "1"])?;
+        Ok(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// Run stty for the terminal of stdin.
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(message.trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Char(char),
+    /// Ctrl-D or an error of input.
+    Quit,
+    Unknown,
+}
+
+fn read_key() -> Key {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut byte = [0];
+
+    // wait for a key over timeouts of `RawMode`.
+    let first = loop {
+        match input.read(&mut byte) {
+            Ok(0) => continue,
+            Ok(_) => break Some(byte[0]),
+            Err(_) => break None,
+        }
+    };
+    let mut next = || match input.read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    };
+
+    match first {
+        None | Some(4) => Key::Quit,
+        Some(b'\r') | Some(b'\n') | Some(b' ') => Key::Enter,
+        // arrows are "ESC [ A" to "ESC [ D", or "ESC O A" to "ESC O D".
+        // The rest of them comes at once, so a lone ESC times out.
+        Some(0x1b) => match (next(), next()) {
+            (Some(b'[' | b'O'), Some(b'A')) => Key::Up,
+            (Some(b'[' | b'O'), Some(b'B')) => Key::Down,
+            (Some(b'[' | b'O'), Some(b'C')) => Key::Right,
+            (Some(b'[' | b'O'), Some(b'D')) => Key::Left,
+            _ => Key::Unknown,
+        },
+        Some(byte) => Key::Char(byte as char),
+    }
+}
+
+/// Escape code to move to (`row`, `column`), 1-origin.
+fn locate(row: i32, column: i32) -> String {
+    format!("\x1b[{};{}H", row, column)
+}
+
+/// Escape codes to draw the whole screen.
+fn render(board: &UiBoard, cursor: Position, status: &str) -> String {
+    let mut screen = String::from("\x1b[H\x1b[2J");
+    let last = board
+        .history()
+        .iter()
+        .rev()
+        .find(|hist| matches!(hist.action, Action::Move(_)));
+    let last_move = last.map(|hist| hist.action);
+    let flipped = last.map_or(0, |hist| hist.flipped.as_bits());
+    let turn_color = if board.is_black_turn() { BLACK } else { WHITE };
+
+    // each square is 4 columns wide, enough for a wide stone.
+    for x in 0..8 {
+        let _ = write!(
+            screen,
+            "{}{}",
+            locate(1, 4 + 4 * x),
+            (b'a' + x as u8) as char
+        );
+    }
+    for y in 0..8 {
+        let _ = write!(screen, "{}{}", locate(y + 2, 1), y + 1);
+
+        for x in 0..8 {
+            let pos = Position::from_xy(x, y).unwrap();
+            let (row, column) = (y + 2, 3 + 4 * x);
+            let background = if last_move == Some(Action::Move(pos)) {
+                LAST_MOVE
+            } else if flipped & pos.as_bits() != 0 {
+                FLIPPED
+            } else {
+                BOARD
+            };
+            let (color, stone) = match board.color_at(pos) {
+                Color::Black => (BLACK, "●"),
+                Color::White => (WHITE, "●"),
+                Color::Empty if board.is_legal_move(pos) => (turn_color, "*"),
+                Color::Empty => (BLACK, "·"),
+            };
+            let (left, right) = if pos == cursor {
+                ("[", "]")
+            } else {
+                (" ", " ")
+            };
+
+            // paint the square first, as the stone may be 1 or 2 columns.
+            let _ = write!(screen, "{}{}{}    ", locate(row, column), background, color);
+            let _ = write!(screen, "{}{}{}", locate(row, column), left, stone);
+            let _ = write!(screen, "{}{}{}", locate(row, column + 3), right, RESET);
+        }
+    }
+
+    // side panel
+    let mark = |turn| if board.turn() == Some(turn) { ">" } else { " " };
+    let _ = write!(
+        screen,
+        "{}{} Black {:>2}{}{} White {:>2}",
+        locate(1, PANEL_COLUMN),
+        mark(Turn::Black),
+        board.count_black(),
+        locate(2, PANEL_COLUMN),
+        mark(Turn::White),
+        board.count_white(),
+    );
+    if board.is_game_over() {
+        let _ = write!(screen, "{}Game over", locate(3, PANEL_COLUMN));
+    }
+    let _ = write!(screen, "{}Moves", locate(4, PANEL_COLUMN));
+
+    let moves: Vec<String> = board
+        .history()
+        .iter()
+        .zip(1..)
+        .map(|(hist, ply)| match hist.action {
+            Action::Move(pos) => format!("{:>2}.{:<5}", ply, pos.to_string()),
+            _ => format!("{:>2}.{:<5}", ply, "pass"),
+        })
+        .collect();
+    let lines: Vec<String> = moves
+        .chunks(MOVES_PER_LINE)
+        .map(|line| line.concat())
+        .collect();
+
+    for (line, row) in lines
+        .iter()
+        .skip(lines.len().saturating_sub(MOVE_LINES))
+        .zip(5..)
+    {
+        let _ = write!(screen, "{}{}", locate(row, PANEL_COLUMN), line.trim_end());
+    }
+
+    let _ = write!(screen, "{}{}", locate(STATUS_ROW, 1), status);
+    let _ = write!(screen, "{}{}", locate(STATUS_ROW + 1, 1), KEYS);
+    screen
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transcript;
+    use std::str::FromStr;
+
+    #[test]
+    fn render_highlights_cursor_last_move_and_moves() {
+        let board = transcript::parse("f5d6").unwrap();
+        let cursor = Position::from_str("c3").unwrap();
+        let screen = render(&board, cursor, "Thinking");
+
+        // the cursor at c3, a legal move of black.
+        assert!(screen.contains(&format!("\x1b[4;11H{}{}    \x1b[4;11H[*", BOARD, BLACK)));
+        // d6 is the last move, which flipped d5.
+        assert!(screen.contains(&format!("\x1b[7;15H{}{}", LAST_MOVE, WHITE)));
+        assert!(screen.contains(&format!("\x1b[6;15H{}{}", FLIPPED, WHITE)));
+        assert!(screen.contains(" 1.f5    2.d6"));
+        assert!(screen.contains("> Black  3"));
+        assert!(screen.contains("Thinking"));
+    }
+}
@@ -11,8 +11,11 @@ pub enum Color {
 }
 
 pub struct UiBoard {
+    start: Board,
     board: Board,
     history: Vec<History>,
+    /// Moves taken back by `undo`, the latest last.
+    undone: Vec<Position>,
     whatnow: Option<Turn>,
 }
 
@@ -24,18 +27,34 @@ impl Default for UiBoard {
 
 impl UiBoard {
     pub fn new() -> Self {
-        let board = Board::new();
-        UiBoard {
+        Self::from_board(Board::new())
+    }
+
+    /// Start a game from `board` instead of the initial position.
+    pub fn from_board(board: Board) -> Self {
+        let mut ui = UiBoard {
+            start: board.clone(),
             board,
             history: Vec::new(),
-            whatnow: Some(Turn::Black),
-        }
+            undone: Vec::new(),
+            whatnow: None,
+        };
+        ui.update_satus();
+        ui
     }
 
     ////////////////////////////////////////////////////////////////
     // Mutable functions
 
+    /// Put a stone at `pos`.  Moves taken back can't be redone after
+    /// this.
     pub fn put_stone(&mut self, pos: Position) -> Result<&Self, &str> {
+        self.play_move(pos)?;
+        self.undone.clear();
+        Ok(self)
+    }
+
+    fn play_move(&mut self, pos: Position) -> Result<(), &'static str> {
         if self.is_game_over() {
             return Err("Game over");
         }
@@ -47,7 +66,7 @@ impl UiBoard {
             ));
             self.board.put_stone(pos.as_bits());
             self.update_satus();
-            Ok(self)
+            Ok(())
         } else {
             Err("Invalid move")
         }
@@ -71,6 +90,71 @@ impl UiBoard {
         }
     }
 
+    /// Take back the last move, with the passes after it.
+    pub fn undo(&mut self) -> Result<&Self, &str> {
+        self.undo_move()?;
+        Ok(self)
+    }
+
+    /// Play the move taken back last by `undo` again.
+    pub fn redo(&mut self) -> Result<&Self, &str> {
+        self.redo_move()?;
+        Ok(self)
+    }
+
+    /// Take back moves until the turn of `turn` comes, so that the
+    /// player of `turn` can retry the last move.
+    pub fn undo_until(&mut self, turn: Turn) -> Result<&Self, &str> {
+        let undone = self.undone.len();
+
+        while self.undo_move().is_ok() {
+            if self.whatnow == Some(turn) {
+                return Ok(self);
+            }
+        }
+        // no earlier turn of `turn`; leave the board as it was.
+        while self.undone.len() > undone && self.redo_move().is_ok() {}
+        Err("Nothing to undo")
+    }
+
+    /// Redo moves until the turn of `turn` comes again, or no more
+    /// move to redo.
+    pub fn redo_until(&mut self, turn: Turn) -> Result<&Self, &str> {
+        self.redo_move()?;
+
+        while self.whatnow != Some(turn) && self.redo_move().is_ok() {}
+        Ok(self)
+    }
+
+    fn undo_move(&mut self) -> Result<(), &'static str> {
+        let last_move = self
+            .history
+            .iter()
+            .rposition(|hist| matches!(hist.action, Action::Move(_)))
+            .ok_or("Nothing to undo")?;
+
+        for hist in self.history.split_off(last_move).iter().rev() {
+            if let Action::Move(pos) = hist.action {
+                let flipped = hist.flipped.as_bits();
+                let (player, opponent) = match hist.turn {
+                    Turn::Black => (&mut self.board.black, &mut self.board.white),
+                    Turn::White => (&mut self.board.white, &mut self.board.black),
+                };
+                *player ^= pos.as_bits() | flipped;
+                *opponent ^= flipped;
+                self.undone.push(pos);
+            }
+            self.board.turn = hist.turn;
+        }
+        self.whatnow = self.turn();
+        Ok(())
+    }
+
+    fn redo_move(&mut self) -> Result<(), &'static str> {
+        let pos = self.undone.pop().ok_or("Nothing to redo")?;
+        self.play_move(pos)
+    }
+
     fn update_satus(&mut self) {
         if self.is_game_over() {
             self.whatnow = None;
@@ -94,6 +178,11 @@ impl UiBoard {
         &self.board
     }
 
+    /// Board before the first action of `history`.
+    pub fn start_board(&self) -> &Board {
+        &self.start
+    }
+
     ////////////////////////////////////////////////////////////////
     // Count and examine stones
 
@@ -154,11 +243,16 @@ impl UiBoard {
     }
 
     pub fn last_action(&self, turn: Turn) -> Option<Action> {
-        if let Some(hist) = self.history.iter().rev().find(|h| h.turn == turn) {
-            Some(hist.action)
-        } else {
-            None
-        }
+        self.history
+            .iter()
+            .rev()
+            .find(|h| h.turn == turn)
+            .map(|hist| hist.action)
+    }
+
+    /// Every action from the beginning, including automatic passes.
+    pub fn history(&self) -> &[History] {
+        &self.history
     }
 
     ////////////////////////////////////////////////////////////////
@@ -214,3 +308,50 @@ impl fmt::Display for UiBoard {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transcript;
+    use std::str::FromStr;
+
+    #[test]
+    fn undo_and_redo_restore_positions() {
+        // black passes after f8.
+        let mut board = transcript::parse("e6f6g6g7c4h6h8f8d6").unwrap();
+        let end = board.raw_board().clone();
+
+        board.undo().unwrap();
+        assert_eq!(
+            transcript::emit(&board, true).unwrap(),
+            "e6f6g6g7c4h6h8f8pa"
+        );
+        board.undo().unwrap();
+        assert_eq!(transcript::emit(&board, true).unwrap(), "e6f6g6g7c4h6h8");
+        assert_eq!(board.whatnow(), Some(Turn::White));
+
+        // white moves again after the pass of black.
+        board.redo_until(Turn::White).unwrap();
+        assert_eq!(
+            transcript::emit(&board, true).unwrap(),
+            "e6f6g6g7c4h6h8f8pa"
+        );
+        board.redo().unwrap();
+        assert_eq!(board.raw_board(), &end);
+        assert!(board.redo().is_err());
+
+        // black takes back h8 over the moves of white.
+        board.undo_until(Turn::Black).unwrap();
+        assert_eq!(transcript::emit(&board, true).unwrap(), "e6f6g6g7c4h6");
+        board.put_stone(Position::from_str("h8").unwrap()).unwrap();
+        assert!(board.redo().is_err());
+
+        let mut board = UiBoard::new();
+        assert!(board.undo().is_err());
+        board.put_stone(Position::from_str("f5").unwrap()).unwrap();
+        assert!(board.undo_until(Turn::White).is_err());
+        assert_eq!(board.history().len(), 1);
+        board.undo().unwrap();
+        assert_eq!(board.raw_board(), &Board::new());
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
 
 #[allow(unused_imports)]
@@ -27,3 +28,40 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+/// Current time in milliseconds.
+///
+/// `std::time::Instant` is not available on `wasm32-unknown-unknown`,
+/// so WASM asks JavaScript for the clock instead.
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+/// Current time in milliseconds since the first call.
+///
+/// Unlike `SystemTime`, `Instant` never goes back with the wall clock.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+/// Time since `start` of `now_ms`.
+pub fn elapsed_since(start: f64) -> Duration {
+    let elapsed = now_ms() - start;
+    // the clock of JavaScript goes back with the wall clock.
+    #[cfg(target_arch = "wasm32")]
+    let elapsed = elapsed.max(0.0);
+
+    Duration::from_secs_f64(elapsed / 1000.0)
+}
+
+/// Time of `now_ms` after `budget` from now.
+pub fn deadline_after(budget: Duration) -> f64 {
+    now_ms() + budget.as_secs_f64() * 1000.0
+}
@@ -7,7 +7,7 @@ use wasm_bindgen::prelude::*;
 
 macro_rules! message {
     ( $id:expr, $( $t:tt )* ) => {
-        screen_show_message($id, &format!( $( $t )* ).into());
+        screen_show_message($id, &format!( $( $t )* ).into())
     }
 }
 
@@ -48,7 +48,7 @@ impl WasmScreen {
             Some(Action::GiveUp) => message!(name, "Give up"),
             Some(Action::Pass) => message!(name, "Pass"),
             Some(Action::Move(pos)) => message!(name, "Move {}", pos),
-            None => (),
+            Some(Action::Undo) | Some(Action::Redo) | None => (),
         }
     }
 
@@ -0,0 +1,276 @@
+//! Reader of WTHOR databases, the standard archive of tournament games.
+//!
+//! A database is a set of binary files, each starting with a 16-byte
+//! header:
+//!
+//! * `.wtb`: games in 68-byte records; tournament, black and white
+//!   player numbers (u16 each), black discs, theoretical black discs
+//!   and 60 moves as `10 * row + column` (1-origin, 0 after the end).
+//! * `.jou`: player names in 20-byte records.
+//! * `.trn`: tournament names in 26-byte records.
+//!
+//! Numbers are little endian, and names are NUL-padded Latin-1.
+//!
+
+use crate::board::Board;
+use crate::position::Position;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+const HEADER_SIZE: usize = 16;
+const GAME_SIZE: usize = 68;
+const PLAYER_SIZE: usize = 20;
+const TOURNAMENT_SIZE: usize = 26;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// Date of the file as (year, month, day).
+    pub created: (u16, u8, u8),
+    /// Number of games in a `.wtb` file.
+    pub games: u32,
+    /// Number of names in a `.jou` or `.trn` file.
+    pub names: u16,
+    /// Year of the games.
+    pub year: u16,
+    /// Depth of perfect play behind theoretical scores.
+    pub depth: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    pub tournament: String,
+    pub black: String,
+    pub white: String,
+    pub year: u16,
+    /// Black discs at the end of the game.
+    pub black_discs: u8,
+    /// Black discs by perfect play from `depth` empties before the end.
+    pub theoretical_discs: u8,
+    /// Moves without passes.
+    pub moves: Vec<Position>,
+}
+
+impl Game {
+    /// Moves such as "f5d6c3", as read by `transcript::parse`.
+    pub fn transcript(&self) -> String {
+        self.moves.iter().map(|pos| pos.to_string()).collect()
+    }
+}
+
+pub struct Database {
+    header: Header,
+    records: Vec<u8>,
+    players: Vec<String>,
+    tournaments: Vec<String>,
+}
+
+impl Database {
+    /// Load games from a `.wtb` file.  Names are empty unless loaded
+    /// by `load_players` and `load_tournaments`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(fs::File::open(path)?)
+    }
+
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let (header, records) = read_file(&mut reader, GAME_SIZE)?;
+
+        Ok(Database {
+            header,
+            records,
+            players: Vec::new(),
+            tournaments: Vec::new(),
+        })
+    }
+
+    /// Load player names from a `.jou` file.
+    pub fn load_players(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.players = read_names(fs::File::open(path)?, PLAYER_SIZE)?;
+        Ok(())
+    }
+
+    /// Load tournament names from a `.trn` file.
+    pub fn load_tournaments(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.tournaments = read_names(fs::File::open(path)?, TOURNAMENT_SIZE)?;
+        Ok(())
+    }
+
+    pub fn set_players(&mut self, players: Vec<String>) {
+        self.players = players;
+    }
+
+    pub fn set_tournaments(&mut self, tournaments: Vec<String>) {
+        self.tournaments = tournaments;
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len() / GAME_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every game, replayed to be validated.  A broken game is an
+    /// error telling its number (1 for the first) and the reason.
+    pub fn games(&self) -> impl Iterator<Item = Result<Game, String>> + '_ {
+        self.records
+            .chunks(GAME_SIZE)
+            .zip(1..)
+            .map(move |(record, number)| {
+                self.parse_game(record)
+                    .map_err(|reason| format!("game {}: {}", number, reason))
+            })
+    }
+
+    fn parse_game(&self, record: &[u8]) -> Result<Game, String> {
+        let name =
+            |names: &[String], index: u16| names.get(index as usize).cloned().unwrap_or_default();
+        let mut board = Board::new();
+        let mut moves = Vec::new();
+
+        for (&code, ply) in record[8..].iter().zip(1..) {
+            if code == 0 {
+                break;
+            }
+            let (row, col) = (code / 10, code % 10);
+            let pos = Position::from_xy(col as i32 - 1, row as i32 - 1)
+                .ok_or(format!("invalid move code {} at ply {}", code, ply))?;
+
+            if board.legal_moves() == 0 {
+                board.pass();
+            }
+            if !board.is_legal_move(pos.as_bits()) {
+                return Err(format!("illegal move {} at ply {}", pos, ply));
+            }
+            board.put_stone(pos.as_bits());
+            moves.push(pos);
+        }
+
+        Ok(Game {
+            tournament: name(&self.tournaments, u16_at(record, 0)),
+            black: name(&self.players, u16_at(record, 2)),
+            white: name(&self.players, u16_at(record, 4)),
+            year: self.header.year,
+            black_discs: record[6],
+            theoretical_discs: record[7],
+            moves,
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////
+// Private
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Read a header and the records of `record_size` bytes after it.
+fn read_file(reader: &mut impl Read, record_size: usize) -> io::Result<(Header, Vec<u8>)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    if bytes.len() < HEADER_SIZE {
+        return Err(invalid("too short for a WTHOR header"));
+    }
+    let header = Header {
+        created: (bytes[0] as u16 * 100 + bytes[1] as u16, bytes[2], bytes[3]),
+        games: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        names: u16_at(&bytes, 8),
+        year: u16_at(&bytes, 10),
+        depth: bytes[14],
+    };
+    let records = bytes.split_off(HEADER_SIZE);
+
+    if records.len() % record_size != 0 {
+        return Err(invalid("truncated WTHOR record"));
+    }
+    Ok((header, records))
+}
+
+fn read_names(mut reader: impl Read, record_size: usize) -> io::Result<Vec<String>> {
+    let (_, records) = read_file(&mut reader, record_size)?;
+
+    Ok(records
+        .chunks(record_size)
+        .map(|record| {
+            // Latin-1 maps to the first 256 code points.
+            let name: String = record
+                .iter()
+                .take_while(|&&b| b != 0)
+                .map(|&b| b as char)
+                .collect();
+            name.trim().to_string()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn header(games: u32, names: u16) -> Vec<u8> {
+        let mut bytes = vec![20, 24, 5, 17];
+        bytes.extend_from_slice(&games.to_le_bytes());
+        bytes.extend_from_slice(&names.to_le_bytes());
+        bytes.extend_from_slice(&2024_u16.to_le_bytes());
+        bytes.extend_from_slice(&[8, 0, 22, 0]);
+        bytes
+    }
+
+    fn game(tournament: u16, black: u16, white: u16, moves: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for number in &[tournament, black, white] {
+            bytes.extend_from_slice(&number.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[33, 32]);
+        bytes.extend_from_slice(moves);
+        bytes.resize(GAME_SIZE, 0);
+        bytes
+    }
+
+    fn names(names: &[&str], size: usize) -> Vec<u8> {
+        let mut bytes = header(0, names.len() as u16);
+        for name in names {
+            let mut record = name.as_bytes().to_vec();
+            record.resize(size, 0);
+            bytes.extend(record);
+        }
+        bytes
+    }
+
+    #[test]
+    fn games_are_read_and_validated() {
+        let mut wtb = header(2, 0);
+        // f5 d6 c3; f5 f5
+        wtb.extend(game(1, 0, 1, &[56, 64, 33]));
+        wtb.extend(game(0, 1, 0, &[56, 56]));
+
+        let mut db = Database::read_from(&wtb[..]).unwrap();
+        db.set_players(read_names(&names(&["Alice", "Bob"], 20)[..], 20).unwrap());
+        db.set_tournaments(read_names(&names(&["Open", "Cup"], 26)[..], 26).unwrap());
+
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.header().created, (2024, 5, 17));
+        assert_eq!(db.header().year, 2024);
+
+        let games: Vec<_> = db.games().collect();
+        let first = games[0].as_ref().unwrap();
+        assert_eq!(first.transcript(), "f5d6c3");
+        assert_eq!((&*first.black, &*first.white), ("Alice", "Bob"));
+        assert_eq!(first.tournament, "Cup");
+        assert_eq!(first.black_discs, 33);
+        assert_eq!(
+            games[1].as_ref().unwrap_err(),
+            "game 2: illegal move f5 at ply 2"
+        );
+
+        assert!(Database::read_from(&wtb[..30]).is_err());
+    }
+}
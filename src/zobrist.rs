@@ -0,0 +1,108 @@
+//! Zobrist hashing of `Board`.
+//!
+//! The hash of a board is the XOR of a random key for every stone on
+//! it (one key table for each color) and a key for white to move.
+//! Since XOR is its own inverse, a hash can be updated incrementally
+//! by XORing only the keys of stones that have changed.
+//!
+
+use crate::bitboard::BitBoard;
+use crate::board::{Board, Turn, Undo};
+
+/// Random keys for each square: `KEYS[0]` for black, `KEYS[1]` for white.
+static KEYS: [[u64; 64]; 2] = generate_keys();
+
+/// XORed into the hash when white is to move.
+const TURN_KEY: u64 = 0xf1bb_cdcb_7a3c_4d95;
+
+/// Zobrist hash of `board`.
+pub fn hash(board: &Board) -> u64 {
+    let mut hash = hash_bits(board.black, 0) ^ hash_bits(board.white, 1);
+
+    if board.turn == Turn::White {
+        hash ^= TURN_KEY;
+    }
+    hash
+}
+
+/// Hash of `child` computed from `hash` of its parent `parent`.
+///
+/// Only changed stones are visited, so it is much cheaper than
+/// `hash(child)` when `child` is one move (or pass) away from `parent`.
+pub fn update(hash: u64, parent: &Board, child: &Board) -> u64 {
+    let mut hash =
+        hash ^ hash_bits(parent.black ^ child.black, 0) ^ hash_bits(parent.white ^ child.white, 1);
+
+    if parent.turn != child.turn {
+        hash ^= TURN_KEY;
+    }
+    hash
+}
+
+/// Hash after `Board::make_move` (or `make_pass`) returned `undo`,
+/// computed from `hash` before it.
+///
+/// Applying the same `undo` again restores the hash, as
+/// `Board::unmake_move` restores the board.
+pub fn update_by_undo(hash: u64, undo: &Undo) -> u64 {
+    let (mover, other) = if undo.turn.is_black() { (0, 1) } else { (1, 0) };
+
+    hash ^ hash_bits(undo.mov | undo.flipped, mover) ^ hash_bits(undo.flipped, other) ^ TURN_KEY
+}
+
+fn hash_bits(mut bits: BitBoard, color: usize) -> u64 {
+    let mut hash = 0;
+
+    while bits != 0 {
+        let index = bits.trailing_zeros();
+        hash ^= KEYS[color][index as usize];
+        bits &= bits - 1;
+    }
+    hash
+}
+
+/// Fill key tables by SplitMix64 with a fixed seed, so that hashes are
+/// stable across runs.
+const fn generate_keys() -> [[u64; 64]; 2] {
+    let mut keys = [[0; 64]; 2];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut i = 0;
+
+    while i < 128 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i / 64][i % 64] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        let mut board = Board::new();
+        let mut hash = hash(&board);
+        let mut undo_hash = hash;
+
+        while !board.is_game_over() {
+            // always take the last child, so that the game includes passes
+            let child = board.children().pop().unwrap();
+            hash = update(hash, &board, &child);
+
+            let undo = match board.legal_move_iter().last() {
+                Some(mov) => board.make_move(mov),
+                None => board.make_pass(),
+            };
+            undo_hash = update_by_undo(undo_hash, &undo);
+
+            assert_eq!(board, child);
+            assert_eq!(hash, super::hash(&board));
+            assert_eq!(undo_hash, hash);
+        }
+    }
+}